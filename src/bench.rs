@@ -0,0 +1,217 @@
+// Headless benchmark mode. Runs sorts with no window and no sleeping, and prints how much work each one did.
+// Usage: sort_visualiser bench [--sizes 100,1000] [--distributions random,sorted] [--sorts quick,merge]
+//                              [--repeats 1] [--format csv|json] [--output FILE] [--seed SEED]
+
+use std::fs;
+use std::io::{self, ErrorKind, Write};
+use std::process;
+use std::str::FromStr;
+
+use crate::{
+    config::{self, Config},
+    sorting_array::{
+        algorithms::{self, SortAlgorithm},
+        context::CounterSnapshot,
+        distribution::{Distribution, ALL_DISTRIBUTIONS},
+    },
+};
+
+const USAGE: &str = "Usage: sort_visualiser bench [--sizes 100,1000] [--distributions random,sorted,reversed,...] \
+[--sorts bubble,quick,...] [--repeats 1] [--format csv|json] [--output FILE] [--seed SEED]";
+
+#[derive(Copy, Clone, PartialEq)]
+enum OutputFormat {
+    Csv,
+    Json,
+}
+
+struct BenchOptions {
+    sizes: Vec<usize>,
+    distributions: Vec<Distribution>,
+    sorts: Option<Vec<String>>,     // Sort ids. All sorts if None.
+    repeats: usize,
+    format: OutputFormat,
+    output: Option<String>,
+    seed: Option<u64>,      // Overrides the seed in the config
+}
+
+impl BenchOptions {
+    fn parse(args: &[String]) -> io::Result<Self> {
+        fn invalid(message: String) -> io::Error {
+            io::Error::new(ErrorKind::InvalidInput, message)
+        }
+
+        fn parse_number(value: &str, flag: &str) -> io::Result<usize> {
+            value.parse::<usize>()
+                .map_err(|_| invalid(format!("Could not parse {} in {} as a positive integer.", value, flag)))
+        }
+
+        let mut options = Self {
+            sizes: vec![100, 1000],
+            distributions: ALL_DISTRIBUTIONS.to_vec(),
+            sorts: None,
+            repeats: 1,
+            format: OutputFormat::Csv,
+            output: None,
+            seed: None,
+        };
+
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args.next()
+                .ok_or_else(|| invalid(format!("Missing value for {}", flag)))?;
+
+            match flag.as_str() {
+                "--sizes" => {
+                    options.sizes = value.split(',')
+                        .map(|size| parse_number(size, flag))
+                        .collect::<io::Result<_>>()?;
+                },
+                "--distributions" => {
+                    options.distributions = value.split(',')
+                        .map(Distribution::from_str)
+                        .collect::<io::Result<_>>()?;
+                },
+                "--sorts" => options.sorts = Some(value.split(',').map(String::from).collect()),
+                "--repeats" => options.repeats = parse_number(value, flag)?,
+                "--format" => {
+                    options.format = match value.to_lowercase().as_str() {
+                        "csv" => OutputFormat::Csv,
+                        "json" => OutputFormat::Json,
+                        x => return Err(invalid(format!("Invalid format: {}. Options are: csv, json", x))),
+                    }
+                },
+                "--output" => options.output = Some(value.clone()),
+                "--seed" => options.seed = Some(
+                    value.parse::<u64>()
+                        .map_err(|_| invalid(format!("Could not parse {} in {} as an integer of 0 or more.", value, flag)))?
+                ),
+                x => return Err(invalid(format!("Unknown option: {}", x))),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+struct BenchResult {
+    algorithm: String,
+    distribution: Distribution,
+    size: usize,
+    run: usize,
+    counters: CounterSnapshot,
+    sorted: bool,   // If the output was the sorted input
+}
+
+pub fn run(args: &[String]) {
+    let options = BenchOptions::parse(args).unwrap_or_else(|err| {
+        eprintln!("{}\n{}", err, USAGE);
+        process::exit(1);
+    });
+    let config = Config::from(&config::load_config_file().expect("Could not load config file.")[0]);
+    if let Some(seed) = options.seed {
+        config.sorts.rng.reseed(seed);
+    }
+    eprintln!("Seed: {}", config.sorts.rng.seed());
+
+    if let Some(ids) = options.sorts.as_ref() {
+        let valid: Vec<&str> = config.sorts.iter().map(|sort| sort.algorithm.id()).collect();
+        if let Some(unknown) = ids.iter().find(|id| !valid.contains(&id.as_str())) {
+            eprintln!("Unknown sort: {}. Options are: {}\n{}", unknown, valid.join(", "), USAGE);
+            process::exit(1);
+        }
+    }
+
+    // Sorts with options are benchmarked with every combination of them.
+    let selected: Vec<_> = config.sorts.iter()
+        .filter(|sort| options.sorts.as_ref().map_or(true, |ids| ids.iter().any(|id| id == sort.algorithm.id())))
+        .collect();
+    let variants: Vec<Vec<Box<dyn SortAlgorithm>>> = selected.iter()
+        .map(|sort| sort.algorithm.variants())
+        .collect();
+    let algorithms: Vec<&dyn SortAlgorithm> = selected.iter()
+        .zip(variants.iter())
+        .flat_map(|(sort, variants)| {
+            if variants.is_empty() {
+                vec![sort.algorithm.as_ref()]
+            } else {
+                variants.iter().map(|variant| variant.as_ref()).collect()
+            }
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for &algorithm in algorithms.iter() {
+        for &size in options.sizes.iter() {
+            for &distribution in options.distributions.iter() {
+                for run in 0..options.repeats {
+                    let input = distribution.generate(size, &config.sorts.rng);
+                    let mut expected = input.clone();
+                    expected.sort_unstable();
+
+                    let (output, counters) = algorithms::run_headless(algorithm, input, &config.sorts);
+
+                    results.push(BenchResult {
+                        algorithm: algorithm.full_name(),
+                        distribution,
+                        size,
+                        run,
+                        counters,
+                        sorted: output == expected,
+                    });
+                }
+            }
+        }
+    }
+
+    let text = match options.format {
+        OutputFormat::Csv => to_csv(&results),
+        OutputFormat::Json => to_json(&results),
+    };
+
+    match options.output {
+        Some(path) => fs::write(&path, text).unwrap_or_else(|err| panic!("Could not write to {}: {}", path, err)),
+        None => io::stdout().write_all(text.as_bytes()).unwrap(),
+    }
+}
+
+fn to_csv(results: &[BenchResult]) -> String {
+    let mut csv = String::from("algorithm,distribution,size,run,comparisons,swaps,reads,writes,time_ms,sorted\n");
+    for result in results {
+        csv.push_str(&format!(
+            "\"{}\",{},{},{},{},{},{},{},{:.3},{}\n",
+            result.algorithm.replace('"', "\"\""),
+            result.distribution.name(),
+            result.size,
+            result.run,
+            result.counters.comparisons,
+            result.counters.swaps,
+            result.counters.reads,
+            result.counters.writes,
+            result.counters.elapsed.as_secs_f64() * 1000.0,
+            result.sorted,
+        ));
+    }
+    csv
+}
+
+fn to_json(results: &[BenchResult]) -> String {
+    let rows: Vec<String> = results.iter()
+        .map(|result| format!(
+            "  {{\"algorithm\": \"{}\", \"distribution\": \"{}\", \"size\": {}, \"run\": {}, \"comparisons\": {}, \"swaps\": {}, \
+\"reads\": {}, \"writes\": {}, \"time_ms\": {:.3}, \"sorted\": {}}}",
+            result.algorithm.replace('\\', "\\\\").replace('"', "\\\""),
+            result.distribution.name(),
+            result.size,
+            result.run,
+            result.counters.comparisons,
+            result.counters.swaps,
+            result.counters.reads,
+            result.counters.writes,
+            result.counters.elapsed.as_secs_f64() * 1000.0,
+            result.sorted,
+        ))
+        .collect();
+
+    format!("[\n{}\n]\n", rows.join(",\n"))
+}
@@ -0,0 +1,111 @@
+// The drawing operations that arrays are displayed with, so that they can be drawn by nannou in the window or by the
+// software rasteriser (see raster.rs) when exporting. Coordinates have the origin in the centre with y going up, like
+// nannou's.
+
+use nannou::draw::Draw;
+use nannou::geom::point::Point2;
+use nannou::color::LinSrgba;
+
+use crate::tools;
+
+pub type Point = [f32; 2];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Colour {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Colour {
+    pub const BLACK: Colour = Colour { r: 0.0, g: 0.0, b: 0.0, a: 1.0 };
+    pub const WHITE: Colour = Colour { r: 1.0, g: 1.0, b: 1.0, a: 1.0 };
+
+    #[inline]
+    pub fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self { r, g, b, a: 1.0 }
+    }
+
+    #[inline]
+    pub fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    // Hue, saturation and value all go from 0 to 1, like nannou's hsv.
+    #[inline]
+    pub fn hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let (r, g, b) = tools::hsv_to_rgb(hue, saturation, value);
+        Self::rgb(r, g, b)
+    }
+}
+
+impl From<(f32, f32, f32)> for Colour {
+    #[inline]
+    fn from((r, g, b): (f32, f32, f32)) -> Self {
+        Self::rgb(r, g, b)
+    }
+}
+
+pub trait Canvas {
+    fn line(&mut self, start: Point, end: Point, weight: f32, colour: Colour);
+    fn rect(&mut self, centre: Point, dims: (f32, f32), colour: Colour);
+    // The outline is centred on the edge of the rectangle.
+    fn rect_outline(&mut self, centre: Point, dims: (f32, f32), weight: f32, colour: Colour);
+    fn tri(&mut self, points: [Point; 3], colour: Colour);
+    fn quad(&mut self, points: [Point; 4], colour: Colour);
+    // `resolution` is the number of sides, or None for a smooth circle.
+    fn ellipse(&mut self, centre: Point, radius: f32, resolution: Option<usize>, colour: Colour);
+}
+
+// Draws with nannou, on the GPU.
+pub struct DrawCanvas<'a>(pub &'a Draw);
+
+impl Canvas for DrawCanvas<'_> {
+    fn line(&mut self, start: Point, end: Point, weight: f32, colour: Colour) {
+        self.0.line()
+            .start(Point2::new(start[0], start[1]))
+            .end(Point2::new(end[0], end[1]))
+            .weight(weight)
+            .rgba(colour.r, colour.g, colour.b, colour.a);
+    }
+
+    fn rect(&mut self, centre: Point, dims: (f32, f32), colour: Colour) {
+        self.0.rect()
+            .x_y(centre[0], centre[1])
+            .w_h(dims.0, dims.1)
+            .rgba(colour.r, colour.g, colour.b, colour.a);
+    }
+
+    fn rect_outline(&mut self, centre: Point, dims: (f32, f32), weight: f32, colour: Colour) {
+        self.0.rect()
+            .x_y(centre[0], centre[1])
+            .w_h(dims.0, dims.1)
+            .no_fill()
+            .stroke(LinSrgba::new(colour.r, colour.g, colour.b, colour.a))
+            .stroke_weight(weight);
+    }
+
+    fn tri(&mut self, points: [Point; 3], colour: Colour) {
+        self.0.tri()
+            .points(points[0], points[1], points[2])
+            .rgba(colour.r, colour.g, colour.b, colour.a);
+    }
+
+    fn quad(&mut self, points: [Point; 4], colour: Colour) {
+        self.0.quad()
+            .points(points[0], points[1], points[2], points[3])
+            .rgba(colour.r, colour.g, colour.b, colour.a);
+    }
+
+    fn ellipse(&mut self, centre: Point, radius: f32, resolution: Option<usize>, colour: Colour) {
+        let drawing = self.0.ellipse()
+            .x_y(centre[0], centre[1])
+            .radius(radius)
+            .rgba(colour.r, colour.g, colour.b, colour.a);
+
+        if let Some(resolution) = resolution {
+            drawing.resolution(resolution);
+        }
+    }
+}
@@ -1,85 +1,95 @@
 use yaml_rust::Yaml;
+use std::io::{self, Read};
+use std::str::FromStr;
 use std::time::Duration;
 use std::sync::Arc;
-use std::str::FromStr;
 
-use crate::sorting_array::{QuickSortType, MergeSortType};
+use crate::sorting_array::{ShuffleType, algorithms::SortRegistry, audio::{Envelope, Waveform}, distribution::Distribution};
+
+const CONFIG_FILE_LOCATION: &str = "./config.yaml";
+
+pub fn load_config_file() -> io::Result<Vec<Yaml>> {
+    use yaml_rust::YamlLoader;
+    use std::fs;
+
+    let mut conf_file_string = String::new();
+    fs::File::open(CONFIG_FILE_LOCATION)?
+        .read_to_string(&mut conf_file_string)?;
+
+    let confs = YamlLoader::load_from_str(&conf_file_string).unwrap();
+    if confs.is_empty() { panic!("Error: Config file is empty.") }
+    Ok(confs)
+}
 
-#[derive(Debug)]
 pub struct Config {
     pub array_len: usize,
     pub multi_array_len: usize,
+    pub distribution: Distribution,
     pub sound_enabled: bool,
-    pub sleep_times: Arc<SleepTimes>,
-    pub radix_base: usize,
-    pub quicksort_partition_type: QuickSortType,
-    pub merge_sort_type: MergeSortType,
-    pub shuffle_passes: u16,
+    pub waveform: Waveform,
+    pub envelope: Envelope,
+    pub minimum_pitch: f64,     // Hz of the smallest value
+    pub maximum_pitch: f64,     // Hz of the largest value
+    pub sorts: Arc<SortRegistry>,
+    pub race_sorts: Vec<usize>,     // Sort ids, in the order they fill race panes
+    pub shuffle_sleep: Duration,
+    pub shuffle_type: ShuffleType,
     pub doughnut_ratio: f32,
+    pub trace_limit: usize,     // Most operations recorded for replaying a sort. 0 turns recording off.
 }
 
 
 impl From<&Yaml> for Config {
     fn from(conf: &Yaml) -> Self {
+        let sorts = SortRegistry::from(conf);
+        let race_sorts = race_sorts(conf, &sorts);
+
         Self {
             array_len: conf["array_length"].as_i64()
                 .expect("Could not parse array_length from config file.") as usize,
             multi_array_len: conf["multi_array_length"].as_i64()
                 .expect("Could not parse multi_array_length from config file.") as usize,
+            distribution: Distribution::from_str(conf["distribution"].as_str()
+                .expect("Could not parse distribution field in config as a string.")
+            ).unwrap(),
             sound_enabled: conf["sound_enabled"].as_bool()
                 .expect("Could not parse sound_enabled from config file as a booleon.") as bool,
-            sleep_times: Arc::new(SleepTimes::from(conf)),
-            radix_base: conf["radix_lsd_base"].as_i64()
-                .expect("Could not parse radix_lsd_base as an integer.") as usize,
-            quicksort_partition_type: QuickSortType::from_str(
-                conf["quicksort_partitioning"].as_str().expect("Could not parse quicksort_partitioning field in config as a string.")
+            waveform: Waveform::from_str(conf["waveform"].as_str()
+                .expect("Could not parse waveform field in config as a string.")
             ).unwrap(),
-            merge_sort_type: MergeSortType::from_str(
-                conf["merge_sort_type"].as_str().expect("Could not parse merge_sort_type field in config as a string.")
+            envelope: Envelope::from(conf),
+            minimum_pitch: conf["minimum_pitch"].as_f64()
+                .expect("Could not parse minimum_pitch field in config as a float."),
+            maximum_pitch: conf["maximum_pitch"].as_f64()
+                .expect("Could not parse maximum_pitch field in config as a float."),
+            sorts: Arc::new(sorts),
+            race_sorts,
+            shuffle_sleep: Duration::from_millis(conf["shuffle_sleep"].as_i64()
+                .expect("Could not parse shuffle_sleep field in config as an integer.") as u64),
+            shuffle_type: ShuffleType::from_str(conf["shuffle_type"].as_str()
+                .expect("Could not parse shuffle_type field in config as a string.")
             ).unwrap(),
-            shuffle_passes: conf["shuffle_passes"].as_i64()
-                .expect("Could not parse shuffle_passes field in config as an integer.") as u16,
             doughnut_ratio: conf["doughnut_ratio"].as_f64()
                 .expect("Could not parse doughnut_ratio as a 64 bit float.") as f32,
+            trace_limit: conf["trace_limit"].as_i64()
+                .expect("Could not parse trace_limit field in config as an integer.") as usize,
         }
     }
 }
 
-#[derive(Debug)]
-pub struct SleepTimes {
-    pub bubble: Duration,
-    pub cocktail: Duration,
-    pub insertion: Duration,
-    pub selection: Duration,
-    pub shell: Duration,
-    pub quick: Duration,
-    pub merge: Duration,
-    pub radix: Duration,
-
-    pub shuffle: Duration,
-}
-
-impl From<&Yaml> for SleepTimes {
-    fn from(conf: &Yaml) -> Self {
-        #[inline]
-        fn get_sleep_time_from_yaml(yaml: &Yaml, sleep_name: &'static str) -> Duration {
-            let yaml_field = &yaml[sleep_name];
-            Duration::from_millis(
-                yaml_field.as_i64()
-                    .unwrap_or_else(|| panic!("Could not parse {} as an integer: {:?}", sleep_name, yaml_field)) as u64
-            )
-        }
+// Sorts listed by key in race_sorts, followed by the rest of the sorts in order so that there are enough for every pane.
+fn race_sorts(conf: &Yaml, sorts: &SortRegistry) -> Vec<usize> {
+    let keys = conf["race_sorts"].as_str()
+        .expect("Could not parse race_sorts field in config as a string.");
 
-        Self {
-            bubble: get_sleep_time_from_yaml(conf, "bubble_sleep"),
-            cocktail: get_sleep_time_from_yaml(conf, "cocktail_shaker_sleep"),
-            insertion: get_sleep_time_from_yaml(conf, "insertion_sleep"),
-            selection: get_sleep_time_from_yaml(conf, "selection_sleep"),
-            shell: get_sleep_time_from_yaml(conf, "shell_sleep"),
-            quick: get_sleep_time_from_yaml(conf, "quick_sleep"),
-            merge: get_sleep_time_from_yaml(conf, "merge_sleep"),
-            radix: get_sleep_time_from_yaml(conf, "radix_sleep"),
-            shuffle: get_sleep_time_from_yaml(conf, "shuffle_sleep"),
+    let mut ids: Vec<usize> = keys.chars()
+        .filter(|c| !c.is_whitespace() && *c != ',')
+        .map(|key| sorts.find_by_key(key).unwrap_or_else(|| panic!("No sort has the key {} given in race_sorts.", key)))
+        .collect();
+    for id in 0..sorts.iter().count() {
+        if !ids.contains(&id) {
+            ids.push(id);
         }
     }
-}
\ No newline at end of file
+    ids
+}
@@ -0,0 +1,369 @@
+// Headless export mode. Runs a sort with no window, then replays its trace with the software rasteriser, writing every
+// frame to an animated GIF or a sequence of PNGs. Needs no GPU. Can also render the sort's sound to a WAV file instead,
+// with the same timing as the frames, so that it can be put together with them in a video, or to a MIDI file.
+// Usage: sort_visualiser export --sort quick [--mode bars] [--size 640x360] [--fps 30] [--ops-per-frame 1]
+//                               [--length 100] [--distribution random] [--input FILE] [--format gif|png|wav|midi] [--output PATH]
+//                               [--seed SEED]
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+
+use crate::{
+    canvas::Colour,
+    config::{self, Config},
+    raster::Raster,
+    sorting_array::{
+        DataArrWrapper, DisplayMode,
+        algorithms,
+        audio::{self, Audio},
+        distribution::Distribution,
+        file,
+        midi::{self, Note},
+        trace::TracePlayer,
+    },
+};
+
+const USAGE: &str = "Usage: sort_visualiser export --sort quick [--mode bars] [--size 640x360] [--fps 30] [--ops-per-frame 1] \
+[--length 100] [--distribution random] [--input FILE] [--format gif|png|wav|midi] [--output PATH] [--seed SEED]";
+const GIF_SPEED: i32 = 10;      // 1 (best colours) to 30 (fastest)
+const END_HOLD_SECS: f32 = 1.0; // How long the sorted array is shown for at the end
+
+#[derive(Copy, Clone, PartialEq)]
+enum ExportFormat {
+    Frames(ImageFormat),
+    Wav,
+    Midi,
+}
+
+// Formats that the animation can be written as, a frame at a time.
+#[derive(Copy, Clone, PartialEq)]
+enum ImageFormat {
+    Gif,
+    Png,
+}
+
+struct ExportOptions {
+    sort: String,       // Sort id, as in the <id>_sleep config keys
+    mode: DisplayMode,
+    size: (usize, usize),
+    fps: f32,
+    ops_per_frame: usize,
+    length: usize,
+    distribution: Distribution,
+    input: Option<PathBuf>,     // Array file to sort instead of generating one
+    format: ExportFormat,
+    output: Option<PathBuf>,
+    seed: Option<u64>,          // Overrides the seed in the config
+}
+
+impl ExportOptions {
+    fn parse(args: &[String]) -> io::Result<Self> {
+        fn invalid(message: String) -> io::Error {
+            io::Error::new(ErrorKind::InvalidInput, message)
+        }
+
+        fn parse_number(value: &str, flag: &str) -> io::Result<usize> {
+            value.parse::<usize>()
+                .ok()
+                .filter(|&number| number > 0)
+                .ok_or_else(|| invalid(format!("Could not parse {} in {} as a positive integer.", value, flag)))
+        }
+
+        let mut sort = None;
+        let mut options = Self {
+            sort: String::new(),
+            mode: DisplayMode::Bars,
+            size: (640, 360),
+            fps: 30.0,
+            ops_per_frame: 1,
+            length: 100,
+            distribution: Distribution::Random,
+            input: None,
+            format: ExportFormat::Frames(ImageFormat::Gif),
+            output: None,
+            seed: None,
+        };
+
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args.next()
+                .ok_or_else(|| invalid(format!("Missing value for {}", flag)))?;
+
+            match flag.as_str() {
+                "--sort" => sort = Some(value.clone()),
+                "--mode" => options.mode = DisplayMode::from_str(value)?,
+                "--size" => {
+                    let mut dims = value.split('x').map(|dim| parse_number(dim, flag));
+                    options.size = match (dims.next(), dims.next(), dims.next()) {
+                        (Some(width), Some(height), None) => (width?, height?),
+                        _ => return Err(invalid(format!("Could not parse {} in {} as <width>x<height>.", value, flag))),
+                    };
+                },
+                "--fps" => options.fps = parse_number(value, flag)? as f32,
+                "--ops-per-frame" => options.ops_per_frame = parse_number(value, flag)?,
+                "--length" => options.length = parse_number(value, flag)?,
+                "--distribution" => options.distribution = Distribution::from_str(value)?,
+                "--input" => options.input = Some(PathBuf::from(value)),
+                "--format" => {
+                    options.format = match value.to_lowercase().as_str() {
+                        "gif" => ExportFormat::Frames(ImageFormat::Gif),
+                        "png" => ExportFormat::Frames(ImageFormat::Png),
+                        "wav" => ExportFormat::Wav,
+                        "midi" | "mid" => ExportFormat::Midi,
+                        x => return Err(invalid(format!("Invalid format: {}. Options are: gif, png, wav, midi", x))),
+                    }
+                },
+                "--output" => options.output = Some(PathBuf::from(value)),
+                "--seed" => options.seed = Some(
+                    value.parse::<u64>()
+                        .map_err(|_| invalid(format!("Could not parse {} in {} as an integer of 0 or more.", value, flag)))?
+                ),
+                x => return Err(invalid(format!("Unknown option: {}", x))),
+            }
+        }
+
+        // GIFs store their size in 16 bits.
+        let max_gif_size = u16::MAX as usize;
+        let too_big = options.size.0 > max_gif_size || options.size.1 > max_gif_size;
+        if options.format == ExportFormat::Frames(ImageFormat::Gif) && too_big {
+            return Err(invalid(format!("GIFs can be at most {0}x{0}, not {1}x{2}.", max_gif_size, options.size.0, options.size.1)));
+        }
+
+        options.sort = sort.ok_or_else(|| invalid(String::from("--sort is required.")))?;
+        Ok(options)
+    }
+}
+
+// Where frames go. GIFs are written as they are rendered, PNGs to one numbered file each.
+enum FrameWriter {
+    Gif(gif::Encoder<BufWriter<File>>),
+    Png(PathBuf),
+}
+
+impl FrameWriter {
+    // `size` fits in a GIF, see ExportOptions::parse.
+    fn new(format: ImageFormat, size: (usize, usize), path: &Path) -> io::Result<Self> {
+        match format {
+            ImageFormat::Gif => {
+                let file = BufWriter::new(File::create(path)?);
+                let mut encoder = gif::Encoder::new(file, size.0 as u16, size.1 as u16, &[])
+                    .map_err(|err| io::Error::new(ErrorKind::Other, err))?;
+                encoder.set_repeat(gif::Repeat::Infinite)
+                    .map_err(|err| io::Error::new(ErrorKind::Other, err))?;
+                Ok(FrameWriter::Gif(encoder))
+            },
+            ImageFormat::Png => {
+                fs::create_dir_all(path)?;
+                Ok(FrameWriter::Png(path.to_path_buf()))
+            },
+        }
+    }
+
+    // `duration` is how long the frame is shown for, in seconds. PNG sequences have a fixed frame rate, so longer
+    // frames are written more than once.
+    fn write(&mut self, raster: &Raster, frame_num: &mut usize, duration: f32, fps: f32) -> io::Result<()> {
+        match self {
+            FrameWriter::Gif(encoder) => {
+                let mut pixels = raster.pixels().to_vec();
+                let mut frame = gif::Frame::from_rgba_speed(raster.width() as u16, raster.height() as u16, &mut pixels, GIF_SPEED);
+                frame.delay = (duration * 100.0).round().max(1.0) as u16;    // In hundredths of a second
+                encoder.write_frame(&frame)
+                    .map_err(|err| io::Error::new(ErrorKind::Other, err))?;
+                *frame_num += 1;
+            },
+            FrameWriter::Png(dir) => {
+                for _ in 0..(duration * fps).round().max(1.0) as usize {
+                    let file = BufWriter::new(File::create(dir.join(format!("frame_{:05}.png", frame_num)))?);
+                    let mut encoder = png::Encoder::new(file, raster.width() as u32, raster.height() as u32);
+                    encoder.set_color(png::ColorType::RGBA);
+                    encoder.set_depth(png::BitDepth::Eight);
+                    encoder.write_header()
+                        .and_then(|mut writer| writer.write_image_data(raster.pixels()))
+                        .map_err(|err| io::Error::new(ErrorKind::Other, err))?;
+                    *frame_num += 1;
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+pub fn run(args: &[String]) {
+    let options = ExportOptions::parse(args).unwrap_or_else(|err| {
+        eprintln!("{}\n{}", err, USAGE);
+        process::exit(1);
+    });
+    let config = Config::from(&config::load_config_file().expect("Could not load config file.")[0]);
+    if let Some(seed) = options.seed {
+        config.sorts.rng.reseed(seed);
+    }
+
+    let sort = config.sorts.iter()
+        .find(|sort| sort.algorithm.id() == options.sort)
+        .unwrap_or_else(|| {
+            let ids: Vec<&str> = config.sorts.iter().map(|sort| sort.algorithm.id()).collect();
+            eprintln!("Unknown sort: {}. Options are: {}", options.sort, ids.join(", "));
+            process::exit(1);
+        });
+
+    let input = match options.input.as_ref() {
+        Some(path) => file::load(path).unwrap_or_else(|err| panic!("Could not load {}: {}", path.display(), err)),
+        None => options.distribution.generate(options.length, &config.sorts.rng),
+    };
+
+    // Run the sort, then rewind its trace to the start to play it back a frame at a time.
+    let mut data = algorithms::run_headless_traced(sort.algorithm.as_ref(), input, &config.sorts);
+    let mut player = TracePlayer::new(data.trace.take().unwrap());
+    player.seek(0, &mut data);
+
+    let path = options.output.clone().unwrap_or_else(|| match options.format {
+        ExportFormat::Frames(ImageFormat::Gif) => PathBuf::from(format!("{}.gif", options.sort)),
+        ExportFormat::Frames(ImageFormat::Png) => PathBuf::from(format!("{}_frames", options.sort)),
+        ExportFormat::Wav => PathBuf::from(format!("{}.wav", options.sort)),
+        ExportFormat::Midi => PathBuf::from(format!("{}.mid", options.sort)),
+    });
+    eprintln!(
+        "Exporting {} ({} operations, {} per frame) to {}",
+        sort.algorithm.name(),
+        player.len(),
+        options.ops_per_frame,
+        path.display(),
+    );
+
+    let result = match options.format {
+        ExportFormat::Wav => export_wav(&options, &config, &mut data, &mut player, &path),
+        ExportFormat::Midi => export_midi(&options, &config, &mut data, &mut player, &path),
+        ExportFormat::Frames(format) => export(format, &options, &config, &mut data, &mut player, &path),
+    };
+    result.unwrap_or_else(|err| panic!("Could not export to {}: {}", path.display(), err));
+}
+
+fn export(
+    format: ImageFormat,
+    options: &ExportOptions,
+    config: &Config,
+    data: &mut DataArrWrapper,
+    player: &mut TracePlayer,
+    path: &Path,
+) -> io::Result<()> {
+    let mut writer = FrameWriter::new(format, options.size, path)?;
+    let mut raster = Raster::new(options.size.0, options.size.1);
+    let window_dims = (options.size.0 as f32, options.size.1 as f32);
+    let mut frame_num = 0;
+
+    loop {
+        raster.clear(Colour::BLACK);
+        data.draw(
+            &mut raster,
+            0,
+            1,
+            data.len(),
+            options.mode,
+            window_dims,
+            (-window_dims.0 / 2.0, -window_dims.1 / 2.0),
+            config.doughnut_ratio,
+        );
+
+        let finished = step_frame(player, data, options.ops_per_frame);
+        let duration = if finished { END_HOLD_SECS } else { 1.0 / options.fps };
+        writer.write(&raster, &mut frame_num, duration, options.fps)?;
+
+        if finished {
+            break;
+        }
+    }
+
+    if let FrameWriter::Png(_) = writer {
+        eprintln!("Wrote {} frames", frame_num);
+    }
+    Ok(())
+}
+
+// Plays the sound each frame would make, the way the window plays them, with the frames timed as in export.
+fn export_wav(
+    options: &ExportOptions,
+    config: &Config,
+    data: &mut DataArrWrapper,
+    player: &mut TracePlayer,
+    path: &Path,
+) -> io::Result<()> {
+    let mut audio_obj = Audio::new(config.minimum_pitch, config.maximum_pitch, config.waveform, config.envelope);
+    let mut samples = Vec::new();
+    let mut time = 0.0;
+
+    loop {
+        if let Some(ratios) = audio::take_sound(data) {
+            audio_obj.play_chord(&ratios);
+        }
+
+        let finished = step_frame(player, data, options.ops_per_frame);
+        time += if finished { END_HOLD_SECS } else { 1.0 / options.fps } as f64;
+        audio_obj.render_until(&mut samples, time, audio::SAMPLE_RATE);
+
+        if finished {
+            break;
+        }
+    }
+
+    eprintln!("Wrote {:.2}s of audio", time);
+    audio::write_wav(path, &samples, audio::SAMPLE_RATE)
+}
+
+// A note for every change to the active elements, rather than one sound a frame like the window and export_wav, each
+// timed by its operation's place in the frames.
+fn export_midi(
+    options: &ExportOptions,
+    config: &Config,
+    data: &mut DataArrWrapper,
+    player: &mut TracePlayer,
+    path: &Path,
+) -> io::Result<()> {
+    let op_secs = 1.0 / (options.fps as f64 * options.ops_per_frame as f64);
+    let mut notes = Vec::new();
+    let mut time = 0.0;
+
+    loop {
+        for ratio in audio::take_sound(data).unwrap_or_default() {
+            notes.push(Note {
+                start: time,
+                length: audio::SOUND_DURATION.as_secs_f64(),
+                key: midi::key_from_hz(audio::pitch(config.minimum_pitch, config.maximum_pitch, ratio)),
+            });
+        }
+
+        if !player.step_forward(data) {
+            break;
+        }
+        time += op_secs;
+    }
+
+    eprintln!("Wrote {} notes over {:.2}s", notes.len(), time);
+    midi::save(path, &notes)
+}
+
+// Moves the replay on by a frame's worth of operations. Returns true once it has reached the end.
+#[inline]
+fn step_frame(player: &mut TracePlayer, data: &mut DataArrWrapper, ops_per_frame: usize) -> bool {
+    !(0..ops_per_frame).all(|_| player.step_forward(data)) || player.progress() >= 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExportOptions;
+
+    fn parse(args: &str) -> std::io::Result<ExportOptions> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        ExportOptions::parse(&args)
+    }
+
+    #[test]
+    fn gifs_too_big_for_16_bit_sizes_are_rejected() {
+        assert!(parse("--sort quick --size 65535x10").is_ok());
+        assert!(parse("--sort quick --size 65536x10").is_err());
+        assert!(parse("--sort quick --size 10x70000 --format gif").is_err());
+        assert!(parse("--sort quick --size 70000x10 --format png").is_ok());
+    }
+}
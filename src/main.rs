@@ -2,113 +2,180 @@
 extern crate shrinkwraprs;
 
 mod sorting_array;
+mod canvas;
+mod raster;
+mod svg;
 mod tools;
+mod bench;
+mod export;
+mod race;
+mod tui;
 pub mod config;
 
 use nannou::draw::Draw;
 use nannou::prelude::*;
-// use nannou_audio::Buffer;
 use yaml_rust::Yaml;
+use nannou_audio::Buffer;
 
 use crate::{
-    sorting_array::{DisplayMode, SortArray, SortInstruction, audio::{Audio, Waveform}},
+    sorting_array::{
+        ArraySelector, DisplayMode, SortArray, SortInstruction,
+        audio::{self, Audio},
+        distribution::{self, Distribution},
+        file,
+        trace::{TracePlayer, TIMELINE_HEIGHT},
+    },
     config::Config,
+    race::{Race, MIN_PANES, MAX_PANES},
+    svg::Svg,
 };
 
 use std::f32::consts::PI;
-use std::f64::consts::PI as PIf64;
-use std::io::{self, Read};
-use std::str::FromStr;
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
 
-const CONFIG_FILE_LOCATION: &str = "./config.yaml";
 pub const TWO_PI: f32 = 2.0 * PI;
-const SOUND_DURATION: Duration = Duration::from_millis(100);
+const SELECTION_STROKE_WEIGHT: f32 = 3.0;
 
 fn main() {
-    nannou::app(model).update(update).run();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("bench") => bench::run(&args[1..]),
+        Some("tui") => tui::run(),
+        Some("export") => export::run(&args[1..]),
+        _ => nannou::app(model).update(update).run(),
+    }
 }
 
 struct Model {
     arrays: Vec<SortArray>,
     current_display_mode: DisplayMode,
     window_dims: (f32, f32),
-    //audio_stream: nannou_audio::Stream<Audio>,
-    //audio_time_started: Option<Instant>,
+    audio_stream: Option<nannou_audio::Stream<Audio>>,     // None when sound is disabled or there is no output device.
     array_len: usize,
+    distribution: Distribution,     // Used for new arrays. Starts as the one in the config.
     config: Config,
+    player: Option<TracePlayer>,    // Some when replaying the last sort's trace.
+    race: Option<Race>,             // Some when each array is racing a different sort in its own pane.
+    selected: Option<usize>,        // Array that instructions from the keyboard are sent to, instead of every array.
+    show_help: bool,
+    show_heap_tree: bool,   // Draw heaps as a tree over the Bars display.
+    hovered_file: bool,     // A file is being dragged over the window.
 
     shift_key_down: bool,
+    ctrl_key_down: bool,
 }
 
 impl Model {
     fn new() -> io::Result<Self> {
-        let conf = &Self::load_config_file()?[0];
-        let config_obj = Config::from(conf);
+        let conf = &config::load_config_file()?[0];
+        let config_obj = Self::config_from(conf);
  
         // Sound. Sound settings cannot be reloaded without restarting the program.
-        let waveform = Waveform::from_str(conf["waveform"].as_str()
-            .expect("Could not parse waveform field in config as a string.")
-        ).unwrap();
-        let maximum_pitch = conf["maximum_pitch"].as_f64()
-            .expect("Could not parse maximum_pitch field in config as a float.");
-        let minimum_pitch = conf["minimum_pitch"].as_f64()
-            .expect("Could not parse minimum_pitch field in config as a float.");
-
-        // Load audio.
-        /*
-        let audio_host = nannou_audio::Host::new();
-
-        let mut audio_obj = Audio::new(minimum_pitch, maximum_pitch, waveform);
-        if !config_obj.sound_enabled {
-            audio_obj.volume = 0.0;
-        }
-
-        let stream = audio_host
-            .new_output_stream(audio_obj)
-            .render(audio_render)
-            .build()
-            .unwrap();
+        let audio_stream = if config_obj.sound_enabled {
+            let audio_obj = Audio::new(
+                config_obj.minimum_pitch,
+                config_obj.maximum_pitch,
+                config_obj.waveform,
+                config_obj.envelope,
+            );
 
-        stream.pause().unwrap();
-        */
+            nannou_audio::Host::new()
+                .new_output_stream(audio_obj)
+                .render(audio_render)
+                .build()
+                .map_err(|err| eprintln!("Could not open audio output, playing without sound: {}", err))
+                .ok()
+        } else {
+            None
+        };
 
-        Ok(Self {
+        let mut model = Self {
             arrays: vec![SortArray::new(
                 config_obj.array_len,
-                Arc::clone(&config_obj.sleep_times),
+                Arc::clone(&config_obj.sorts),
+                config_obj.distribution,
+                config_obj.shuffle_type,
+                config_obj.shuffle_sleep,
+                config_obj.trace_limit,
             )],
             current_display_mode: DisplayMode::Bars,
             window_dims: (0.0, 0.0),
-            // audio_stream: stream,
-            // audio_time_started: None,
+            audio_stream,
             array_len: config_obj.array_len,
+            distribution: config_obj.distribution,
             config: config_obj,
+            player: None,
+            race: None,
+            selected: None,
+            show_help: false,
+            show_heap_tree: false,
+            hovered_file: false,
             shift_key_down: false,
-        })
-    }
-
-    fn load_config_file() -> io::Result<Vec<Yaml>> {
-        use yaml_rust::YamlLoader;
-        use std::fs;
+            ctrl_key_down: false,
+        };
 
-        let mut conf_file_string = String::new();
-        fs::File::open(CONFIG_FILE_LOCATION)?
-            .read_to_string(&mut conf_file_string)?;
+        if let Some(path) = load_arg() {
+            model.load_file(&path)?;
+        }
+        Ok(model)
+    }
 
-        let confs = YamlLoader::load_from_str(&conf_file_string).unwrap();
-        if confs.is_empty() { panic!("Error: Config file is empty.") }
-        Ok(confs)
+    // Loads the config, with the seed from the command line if one was given.
+    fn config_from(conf: &Yaml) -> Config {
+        let config_obj = Config::from(conf);
+        if let Some(seed) = seed_arg() {
+            config_obj.sorts.rng.reseed(seed);
+        }
+        config_obj
     }
 
     // Sends instruction to all arrays
     fn instruction(&mut self, instruction: SortInstruction) {
-        for arr in self.arrays.iter_mut() {
-            arr.instruction(instruction);
+        self.instruction_to(ArraySelector::All, instruction);
+    }
+
+    fn instruction_to(&mut self, selector: ArraySelector, instruction: SortInstruction) {
+        for (i, arr) in self.arrays.iter_mut().enumerate() {
+            if selector.matches(i) {
+                arr.instruction(instruction);
+            }
         }
     }
 
+    // The selected array, or all of them if none is selected.
+    #[inline]
+    fn selector(&self) -> ArraySelector {
+        self.selected.map_or(ArraySelector::All, ArraySelector::Index)
+    }
+
+    // Only arrays that exist can be selected, and selecting the only array would make no difference.
+    fn select(&mut self, index: Option<usize>) {
+        self.selected = index.filter(|&i| i < self.arrays.len() && self.arrays.len() > 1);
+    }
+
+    // Centre and size of the area an array is drawn in, with the origin in the centre of the window. Arrays that are not
+    // racing are drawn in rows from the bottom, as in the Pixels display.
+    fn array_rect(&self, index: usize) -> (f32, f32, f32, f32) {
+        match self.race.as_ref() {
+            Some(race) => race.pane_rect(index, self.window_dims),
+            None => {
+                let row_height = self.window_dims.1 / self.arrays.len() as f32;
+                (0.0, -self.window_dims.1 / 2.0 + (index as f32 + 0.5) * row_height, self.window_dims.0, row_height)
+            }
+        }
+    }
+
+    fn array_at(&self, x: f32, y: f32) -> Option<usize> {
+        (0..self.arrays.len()).find(|&i| {
+            let (centre_x, centre_y, w, h) = self.array_rect(i);
+            (x - centre_x).abs() <= w / 2.0 && (y - centre_y).abs() <= h / 2.0
+        })
+    }
+
     fn display(&self, draw: &Draw, transform: (f32, f32)) {
         for (i, arr) in self.arrays.iter().enumerate() {
             arr.display(
@@ -125,27 +192,206 @@ impl Model {
     }
 
     fn set_to_single_array(&mut self) {
+        self.race = None;
+        self.selected = None;
         self.arrays.clear();
         self.array_len = self.config.array_len;
         self.arrays.push(SortArray::new(
             self.config.array_len,
-            self.config.sleep_times.clone(),
+            self.config.sorts.clone(),
+            self.distribution,
+            self.config.shuffle_type,
+            self.config.shuffle_sleep,
+            self.config.trace_limit,
         ));
     }
 
     fn set_to_multi_array(&mut self, array_num: usize) {
+        self.race = None;
+        self.selected = None;
         self.arrays.clear();
         for _ in 0..array_num {
             self.arrays.push(SortArray::new(
                 self.config.multi_array_len,
-                self.config.sleep_times.clone(),
+                self.config.sorts.clone(),
+                self.distribution,
+                self.config.shuffle_type,
+                self.config.shuffle_sleep,
+                self.config.trace_limit,
             ));
         }
     }
 
+    // Goes back to the start of the seed's random numbers and regenerates the arrays, so that the same shuffles and
+    // sorts can be run again.
+    fn restart_from_seed(&mut self) {
+        self.instruction(SortInstruction::Stop);
+        self.config.sorts.rng.reseed(self.config.sorts.rng.seed());
+        if self.race.is_some() {
+            self.restart_race();
+        } else {
+            self.instruction(SortInstruction::Reset);
+        }
+    }
+
+    // Switches to the next (or previous) distribution, regenerating all arrays with it.
+    fn cycle_distribution(&mut self, backwards: bool) {
+        self.distribution = self.distribution.cycle(backwards);
+        self.instruction(SortInstruction::Stop);
+
+        if let Some(race) = self.race.as_ref() {
+            let input = self.distribution.generate(race.input().len(), &self.config.sorts.rng);
+            self.start_race(race.panes(), input);
+        } else {
+            for arr in self.arrays.iter_mut() {
+                arr.set_distribution(self.distribution);
+            }
+        }
+    }
+
+    // Splits the window into panes that each sort a copy of the input with a different sort. Sorted input is shuffled
+    // first, so that there is something to race.
+    fn start_race(&mut self, panes: usize, mut input: Vec<usize>) {
+        if input.windows(2).all(|pair| pair[0] <= pair[1]) {
+            distribution::shuffle(&mut input, &self.config.sorts.rng);
+        }
+        if self.player.is_some() {
+            self.toggle_replay();
+        }
+        if self.current_display_mode == DisplayMode::Pixels {
+            self.current_display_mode = DisplayMode::Bars;
+        }
+        self.instruction(SortInstruction::Stop);
+
+        let sorts: Vec<usize> = self.config.race_sorts.iter().take(panes).copied().collect();
+        self.arrays = sorts.iter()
+            .map(|_| SortArray::new(
+                0,      // Filled by the race, so nothing is generated
+                self.config.sorts.clone(),
+                self.distribution,
+                self.config.shuffle_type,
+                self.config.shuffle_sleep,
+                self.config.trace_limit,
+            ))
+            .collect();
+
+        let mut race = Race::new(input, sorts);
+        race.start(&mut self.arrays);
+        self.race = Some(race);
+        self.selected = None;
+    }
+
+    // Races the same input again.
+    fn restart_race(&mut self) {
+        if let Some(race) = self.race.as_ref() {
+            self.start_race(race.panes(), race.input().to_vec());
+        }
+    }
+
+    // Races a new shuffle of the input.
+    fn shuffle_race(&mut self) {
+        if let Some(race) = self.race.as_ref() {
+            let mut input = race.input().to_vec();
+            distribution::shuffle(&mut input, &self.config.sorts.rng);
+            self.start_race(race.panes(), input);
+        }
+    }
+
+    // Enters replay mode with the trace of the last sort, or leaves it if already replaying.
+    fn toggle_replay(&mut self) {
+        if let Some(player) = self.player.take() {
+            self.arrays[0].return_trace(player.into_trace());
+        } else if self.arrays.len() == 1 {
+            if let Some(trace) = self.arrays[0].take_trace() {
+                self.player = Some(TracePlayer::new(trace));
+            }
+        }
+    }
+
+    fn replay_key(&mut self, key: Key) {
+        let player = self.player.as_mut().unwrap();
+        let mut data = self.arrays[0].data.write().unwrap();
+
+        match key {
+            Key::Space => player.playing = !player.playing,
+            Key::Right => {
+                player.playing = false;
+                player.step_forward(&mut data);
+            },
+            Key::Left => {
+                player.playing = false;
+                player.step_back(&mut data);
+            },
+            Key::Period => player.set_direction(true),
+            Key::Comma => player.set_direction(false),
+            Key::Up => player.change_speed(2.0),
+            Key::Down => player.change_speed(0.5),
+            Key::Home => player.seek(0, &mut data),
+            Key::End => player.seek(player.len(), &mut data),
+            _ => (),
+        }
+    }
+
+    // Seeks the trace player to the mouse's x position (mouse coordinates have the origin in the centre of the window).
+    fn scrub_to(&mut self, mouse_x: f32) {
+        if let Some(player) = self.player.as_mut() {
+            let ratio = (mouse_x + self.window_dims.0 / 2.0) / self.window_dims.0;
+            player.seek_ratio(ratio, &mut self.arrays[0].data.write().unwrap());
+        }
+    }
+
+    // Loads an array from a file into a single array, so that its elements get the whole window.
+    fn load_file(&mut self, path: &Path) -> io::Result<()> {
+        let values = file::load(path)?;
+        let name = file::display_name(path);
+
+        if self.player.is_some() {
+            self.toggle_replay();
+        }
+        if self.arrays.len() > 1 {
+            self.set_to_single_array();
+        }
+        if self.current_display_mode == DisplayMode::Pixels {
+            self.current_display_mode = DisplayMode::Bars;
+        }
+
+        self.instruction(SortInstruction::Stop);
+        self.array_len = values.len();
+        self.arrays[0].load(name, values);
+        Ok(())
+    }
+
+    // Saves the array to a new file in the working directory.
+    fn save_file(&self) -> io::Result<PathBuf> {
+        if self.arrays.len() > 1 {
+            return Err(io::Error::new(io::ErrorKind::Other, "Only single arrays can be saved."));
+        }
+
+        let path = file::new_save_path();
+        file::save(&path, &self.arrays[0].values())?;
+        Ok(path)
+    }
+
+    // Saves what the window shows (apart from text) as an SVG.
+    fn save_snapshot(&self) -> io::Result<PathBuf> {
+        let svg = match self.race.as_ref() {
+            Some(race) => {
+                let mut svg = Svg::new(self.window_dims.0, self.window_dims.1);
+                race.draw(&mut svg, &self.arrays, self.current_display_mode, self.window_dims, self.config.doughnut_ratio);
+                svg
+            },
+            None => svg::snapshot(&self.arrays, self.current_display_mode, self.window_dims, self.config.doughnut_ratio),
+        };
+
+        let path = file::timestamped_path("snapshot", "svg");
+        svg.save(&path)?;
+        Ok(path)
+    }
+
     #[inline]
     fn reload_config(&mut self) {
-        self.config = Config::from(&Self::load_config_file().unwrap()[0]);
+        self.config = Self::config_from(&config::load_config_file().unwrap()[0]);
+        self.distribution = self.config.distribution;
         self.set_to_single_array();
     }
 }
@@ -164,55 +410,72 @@ fn update(app: &App, model: &mut Model, _update: Update) {
     let window_rect = app.window_rect();
     model.window_dims = (window_rect.w(), window_rect.h());
 
-    /*
-    if model.audio_stream.is_playing() {
-        if let Some(time_playing) = model.audio_time_started {
-            if time_playing.elapsed() >= SOUND_DURATION {
-                model.audio_stream.pause().unwrap();
-                model.audio_time_started = None;
-            }
-        }
+    if let Some(player) = model.player.as_mut() {
+        player.update(&mut model.arrays[0].data.write().unwrap());
     }
-    
 
-    if model.arrays.len() == 1 {        // If only a single array, then play a sound.
-        let mut write = model.arrays[0].data.write().unwrap();
-        if write.should_play_sound {
-            if let Some(index) = write.active {     // If a sound should be played, and there is a current index
-                let ratio = write[index] as f64/write.max_val as f64;
-                model.audio_stream.send(move |audio| {
-                    audio.hz = audio.min_hz + (audio.max_hz - audio.min_hz) * ratio;    // Interpolate
-                }).unwrap();
-
-                model.audio_stream.play().unwrap();
-                model.audio_time_started = Some(Instant::now());
+    if let Some(race) = model.race.as_mut() {
+        race.update(&model.arrays);
+    }
 
-                write.should_play_sound = false;
-            }
+    if let (Some(stream), 1) = (model.audio_stream.as_ref(), model.arrays.len()) {     // If only a single array, then play a sound.
+        if let Some(ratios) = audio::take_sound(&mut model.arrays[0].data.write().unwrap()) {
+            stream.send(move |audio| audio.play_chord(&ratios)).unwrap();
         }
     }
-    */
 }
 
-fn event(_app: &App, model: &mut Model, event: WindowEvent) {
+fn event(app: &App, model: &mut Model, event: WindowEvent) {
     match event {
         // Keyboard events
+        KeyPressed(key) if model.player.is_some() => {
+            match key {
+                Key::T => model.toggle_replay(),
+                _ => model.replay_key(key),
+            }
+        }
         KeyPressed(key) => {
             match key {
-                Key::LShift => model.shift_key_down = true,
+                Key::LShift | Key::RShift => model.shift_key_down = true,
+                Key::LControl | Key::RControl => model.ctrl_key_down = true,
+                Key::T => model.toggle_replay(),
 
                 Key::S => {
                     if model.shift_key_down {
                         model.current_display_mode = DisplayMode::Spiral
+                    } else if model.race.is_some() && model.selected.is_none() {
+                        model.shuffle_race()
+                    } else {
+                        model.instruction_to(model.selector(), SortInstruction::Shuffle)
+                    }
+                },
+                Key::R => {
+                    if model.shift_key_down {
+                        model.restart_from_seed()
+                    } else if model.race.is_some() && model.selected.is_none() {
+                        model.restart_race()
                     } else {
-                        model.instruction(SortInstruction::Shuffle(model.config.shuffle_passes))
+                        model.instruction_to(model.selector(), SortInstruction::Reset)
                     }
                 },
-                Key::R => model.instruction(SortInstruction::Reset),
-                Key::I => model.instruction(SortInstruction::Reverse),
+                Key::I => model.instruction_to(model.selector(), SortInstruction::Reverse),
+                Key::W => {
+                    if model.shift_key_down {
+                        match model.save_snapshot() {
+                            Ok(path) => println!("Saved snapshot to {}", path.display()),
+                            Err(err) => eprintln!("Could not save snapshot: {}", err),
+                        }
+                    } else {
+                        match model.save_file() {
+                            Ok(path) => println!("Saved array to {}", path.display()),
+                            Err(err) => eprintln!("Could not save array: {}", err),
+                        }
+                    }
+                },
+                Key::V => model.cycle_distribution(model.shift_key_down),
 
                 Key::C | Key::B | Key::D | Key::O | Key::Y | Key::N | Key::L | Key::K => {
-                    if model.arrays.len() > 1 {
+                    if model.arrays.len() > 1 && model.race.is_none() {
                         model.set_to_single_array();
                     }
 
@@ -244,30 +507,86 @@ fn event(_app: &App, model: &mut Model, event: WindowEvent) {
                     model.set_to_multi_array(array_num);
                     model.current_display_mode = DisplayMode::Pixels;
                 }
-                Key::Q => model.instruction(SortInstruction::Stop),
-
-                Key::Key1 => model.instruction(SortInstruction::BubbleSort),
-                Key::Key2 => model.instruction(SortInstruction::CocktailShakerSort),
-                Key::Key3 => model.instruction(SortInstruction::InsertionSort),
-                Key::Key4 => model.instruction(SortInstruction::SelectionSort),
-                Key::Key5 => model.instruction(SortInstruction::ShellSort),
-                Key::Key6 => model.instruction(SortInstruction::QuickSort(model.config.quicksort_partition_type)),
-                Key::Key7 => model.instruction(SortInstruction::MergeSort(model.config.merge_sort_type)),
-                Key::Key9 => model.instruction(SortInstruction::RadixSort(model.config.radix_base)),
-                _ => (),
+                Key::Q => model.instruction_to(model.selector(), SortInstruction::Stop),
+                Key::Space => {
+                    let selector = model.selector();
+                    let paused = model.arrays.iter()
+                        .enumerate()
+                        .any(|(i, arr)| selector.matches(i) && arr.is_paused());
+
+                    if paused {
+                        model.instruction_to(selector, SortInstruction::Resume)
+                    } else {
+                        model.instruction_to(selector, SortInstruction::Pause)
+                    }
+                },
+                Key::Right => model.instruction_to(model.selector(), SortInstruction::Step),
+
+                Key::H => model.show_help = !model.show_help,
+                Key::G => model.show_heap_tree = !model.show_heap_tree,
+                _ => {
+                    match key_to_char(key) {
+                        // Control and a number selects that array (counting from 1), or every array with 0.
+                        Some(c) if model.ctrl_key_down => {
+                            let number = c.to_digit(10).unwrap() as usize;
+                            model.select(number.checked_sub(1));
+                        },
+                        // Shift and a number races that many sorts, or goes back to a single array with 1.
+                        Some(c) if model.shift_key_down => {
+                            let panes = c.to_digit(10).unwrap() as usize;
+                            if panes == 1 {
+                                model.set_to_single_array();
+                            } else if (MIN_PANES..=MAX_PANES).contains(&panes) {
+                                match model.race.as_ref() {
+                                    Some(race) => model.start_race(panes, race.input().to_vec()),
+                                    None => model.start_race(panes, model.arrays[0].values()),
+                                }
+                            }
+                        },
+                        // Sort keys come from the sort registry
+                        Some(c) => {
+                            if let Some(id) = model.config.sorts.find_by_key(c) {
+                                model.instruction_to(model.selector(), SortInstruction::Sort(id))
+                            }
+                        },
+                        None => (),
+                    }
+                },
             }
         }
         KeyReleased(key) => {
             match key {
-                Key::LShift => model.shift_key_down = false,
+                Key::LShift | Key::RShift => model.shift_key_down = false,
+                Key::LControl | Key::RControl => model.ctrl_key_down = false,
                 _ => (),
             }
         }
 
         // Mouse events
-        MouseMoved(_pos) => {}
+        MouseMoved(pos) => {
+            if model.player.as_ref().map_or(false, |player| player.scrubbing) {
+                model.scrub_to(pos.x);
+            }
+        }
+        MousePressed(MouseButton::Left) => {
+            let mouse_y = app.mouse.y + model.window_dims.1 / 2.0;  // From bottom of window
+            if let Some(player) = model.player.as_mut() {
+                if mouse_y <= TIMELINE_HEIGHT * 2.0 {
+                    player.scrubbing = true;
+                    model.scrub_to(app.mouse.x);
+                }
+            } else {
+                // Clicking a pane or row selects it, and clicking it again deselects it.
+                let index = model.array_at(app.mouse.x, app.mouse.y);
+                model.select(if index == model.selected { None } else { index });
+            }
+        }
         MousePressed(_button) => {}
-        MouseReleased(_button) => {}
+        MouseReleased(_button) => {
+            if let Some(player) = model.player.as_mut() {
+                player.scrubbing = false;
+            }
+        }
         MouseWheel(_amount, _phase) => {}
         MouseEntered => {}
         MouseExited => {}
@@ -279,45 +598,132 @@ fn event(_app: &App, model: &mut Model, event: WindowEvent) {
         // Window events
         Moved(_pos) => {}
         Resized(_size) => {}
-        HoveredFile(_path) => {}
-        DroppedFile(_path) => {}
-        HoveredFileCancelled => {}
+        HoveredFile(_path) => model.hovered_file = true,
+        DroppedFile(path) => {
+            model.hovered_file = false;
+            if let Err(err) = model.load_file(&path) {
+                eprintln!("Could not load {}: {}", path.display(), err);
+            }
+        }
+        HoveredFileCancelled => model.hovered_file = false,
         Focused => {}
         Unfocused => {}
         Closed => {}
     }
 }
 
+// `--seed <seed>` on the command line, which overrides the seed in the config.
+fn seed_arg() -> Option<u64> {
+    let args: Vec<String> = env::args().collect();
+    args.iter()
+        .position(|arg| arg == "--seed")
+        .map(|i| {
+            args.get(i + 1)
+                .and_then(|seed| seed.parse().ok())
+                .expect("Could not parse --seed as an integer of 0 or more.")
+        })
+}
+
+// `--load <file>` on the command line, to start with an array loaded from a file.
+fn load_arg() -> Option<PathBuf> {
+    let args: Vec<String> = env::args().collect();
+    args.iter()
+        .position(|arg| arg == "--load")
+        .map(|i| PathBuf::from(args.get(i + 1).expect("--load needs a file to load.")))
+}
+
+// Characters that sorts can be bound to in the sort registry.
+fn key_to_char(key: Key) -> Option<char> {
+    match key {
+        Key::Key0 => Some('0'),
+        Key::Key1 => Some('1'),
+        Key::Key2 => Some('2'),
+        Key::Key3 => Some('3'),
+        Key::Key4 => Some('4'),
+        Key::Key5 => Some('5'),
+        Key::Key6 => Some('6'),
+        Key::Key7 => Some('7'),
+        Key::Key8 => Some('8'),
+        Key::Key9 => Some('9'),
+        _ => None,
+    }
+}
+
+// Lists the available sorts and their keys in the top right of the window.
+fn display_help(draw: &Draw, model: &Model) {
+    const LINE_HEIGHT: f32 = 18.0;
+
+    let mut lines = vec![String::from("Sorts:")];
+    lines.extend(model.config.sorts.key_list());
+
+    let text = lines.join("\n");
+    let height = LINE_HEIGHT * lines.len() as f32;
+    draw.text(&text)
+        .x_y(0.0, model.window_dims.1 / 2.0 - height / 2.0 - LINE_HEIGHT)
+        .w_h(model.window_dims.0 - 2.0 * LINE_HEIGHT, height)
+        .right_justify()
+        .font_size(14)
+        .color(WHITE);
+}
+
 fn view(app: &App, model: &Model, frame: Frame) {
     let transformation = (-model.window_dims.0 / 2.0, -model.window_dims.1 / 2.0); // Axis starts bottom left corner
 
     let draw = app.draw();
     draw.background().color(BLACK);
 
-    model.display(&draw, transformation);
+    match model.race.as_ref() {
+        Some(race) => race.display(&draw, &model.arrays, model.current_display_mode, model.window_dims, model.config.doughnut_ratio),
+        None => model.display(&draw, transformation),
+    }
+
+    if let Some(player) = model.player.as_ref() {
+        player.display(&draw, model.window_dims, transformation);
+    }
+
+    if model.arrays.len() == 1 {
+        if model.show_heap_tree && model.current_display_mode == DisplayMode::Bars {
+            model.arrays[0].display_heap_tree(&draw, model.window_dims, transformation);
+        }
+        model.arrays[0].display_stats(&draw, model.window_dims);
+    }
+
+    if let Some(index) = model.selected {
+        let (x, y, w, h) = model.array_rect(index);
+        draw.rect()
+            .x_y(x, y)
+            .w_h(w, h)
+            .no_fill()
+            .stroke(WHITE)
+            .stroke_weight(SELECTION_STROKE_WEIGHT);
+
+        if model.race.is_none() {   // Race panes already show their stats
+            model.arrays[index].display_stats(&draw, model.window_dims);
+        }
+    }
+
+    if model.show_help {
+        display_help(&draw, model);
+    }
+
+    if model.hovered_file {
+        draw.text("Drop to load array")
+            .x_y(0.0, 0.0)
+            .w_h(model.window_dims.0, 40.0)
+            .font_size(24)
+            .color(WHITE);
+    }
 
     draw.to_frame(app, &frame).unwrap();
 }
 
-/*
 pub fn audio_render(audio: &mut Audio, buffer: &mut Buffer) {
     let sample_rate = buffer.sample_rate() as f64;
 
     for frame in buffer.frames_mut() {
-        let sin_amp = (2.0 * PIf64 * audio.phase).sin() as f32;
-
-        let waveform_amp = match audio.waveform {
-            Waveform::Sine => sin_amp,
-            Waveform::Haversine => sin_amp.max(0.0),    // > 0.0
-            Waveform::Square => (((sin_amp + 1.0)/2.0).round() - 0.5) * 2.0,
-            Waveform::Triangle => sin_amp.round(),
-        };
-
-        audio.phase += audio.hz / sample_rate;
-        audio.phase %= sample_rate;
+        let amp = audio.next_sample(sample_rate);
         for channel in frame {
-            *channel = waveform_amp * audio.volume;
+            *channel = amp;
         }
     }
 }
-*/
@@ -0,0 +1,203 @@
+// Race mode: the window is split into panes, each sorting a copy of the same input with a different sort.
+
+use nannou::draw::Draw;
+use nannou::prelude::*;
+
+use crate::{
+    canvas::{Canvas, Colour, DrawCanvas},
+    sorting_array::{DisplayMode, SortArray, SortInstruction},
+};
+
+pub const MIN_PANES: usize = 2;
+pub const MAX_PANES: usize = 9;
+const PANE_OUTLINE: Colour = Colour { r: 0.5, g: 0.5, b: 0.5, a: 1.0 };   // Grey
+
+pub struct Race {
+    input: Vec<usize>,
+    sorts: Vec<usize>,              // Sort id for each pane
+    ranks: Vec<Option<usize>>,      // Finishing position of each pane, from 1
+}
+
+impl Race {
+    pub fn new(input: Vec<usize>, sorts: Vec<usize>) -> Self {
+        let ranks = vec![None; sorts.len()];
+        Self { input, sorts, ranks }
+    }
+
+    #[inline]
+    pub fn panes(&self) -> usize {
+        self.sorts.len()
+    }
+
+    #[inline]
+    pub fn input(&self) -> &[usize] {
+        &self.input
+    }
+
+    // Gives every pane a fresh copy of the input and starts its sort.
+    pub fn start(&mut self, arrays: &mut [SortArray]) {
+        for (arr, &id) in arrays.iter_mut().zip(self.sorts.iter()) {
+            arr.set_values(self.input.clone());
+            arr.instruction(SortInstruction::Sort(id));
+        }
+        self.ranks = vec![None; self.sorts.len()];
+    }
+
+    // Ranks the panes that have finished since the last update. Panes that finish between the same two frames are ranked
+    // by their sort times. Cancelled sorts are not ranked.
+    pub fn update(&mut self, arrays: &[SortArray]) {
+        let mut finished: Vec<(usize, f32)> = arrays.iter()
+            .enumerate()
+            .filter(|(i, arr)| self.ranks[*i].is_none() && arr.is_finished())
+            .map(|(i, arr)| (i, arr.counters().elapsed.as_secs_f32()))
+            .collect();
+        finished.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+        let ranked = self.ranks.iter().flatten().count();
+        for (place, (i, _)) in finished.into_iter().enumerate() {
+            self.ranks[i] = Some(ranked + place + 1);
+        }
+    }
+
+    // Centre and size of a pane, with the origin in the centre of the window. Panes are laid out in a grid.
+    pub fn pane_rect(&self, index: usize, window_dims: (f32, f32)) -> (f32, f32, f32, f32) {
+        let (cols, rows) = grid(self.panes());
+        let pane_dims = (window_dims.0 / cols as f32, window_dims.1 / rows as f32);
+
+        (
+            -window_dims.0 / 2.0 + ((index % cols) as f32 + 0.5) * pane_dims.0,
+            window_dims.1 / 2.0 - ((index / cols) as f32 + 0.5) * pane_dims.1,
+            pane_dims.0,
+            pane_dims.1,
+        )
+    }
+
+    // Draws each array in its own pane with its sort's name, counters and rank.
+    pub fn display(&self, draw: &Draw, arrays: &[SortArray], mode: DisplayMode, window_dims: (f32, f32), doughnut_ratio: f32) {
+        self.draw(&mut DrawCanvas(draw), arrays, mode, window_dims, doughnut_ratio);
+
+        for (i, arr) in arrays.iter().enumerate() {
+            let (x, y, w, h) = self.pane_rect(i, window_dims);
+            self.display_stats(&draw.x_y(x, y), arr, self.ranks[i], (w, h));
+        }
+    }
+
+    // Draws every pane's array and outline on any canvas, without the stats.
+    pub fn draw(&self, canvas: &mut impl Canvas, arrays: &[SortArray], mode: DisplayMode, window_dims: (f32, f32), doughnut_ratio: f32) {
+        for (i, arr) in arrays.iter().enumerate() {
+            let (x, y, w, h) = self.pane_rect(i, window_dims);
+
+            arr.data.read().unwrap().draw(
+                canvas,
+                0,
+                1,
+                arr.len(),
+                mode,
+                (w, h),
+                (x - w / 2.0, y - h / 2.0),
+                doughnut_ratio,
+            );
+            canvas.rect_outline([x, y], (w, h), 1.0, PANE_OUTLINE);
+        }
+    }
+
+    fn display_stats(&self, draw: &Draw, arr: &SortArray, rank: Option<usize>, pane_dims: (f32, f32)) {
+        const LINE_HEIGHT: f32 = 16.0;
+        const MARGIN: f32 = 8.0;
+
+        let counters = arr.counters();
+        let mut text = format!(
+            "{}\nComparisons: {}\nSwaps: {}\nTime: {:.2}s",
+            arr.current_sort_name().unwrap_or_else(|| String::from("No sort")),
+            counters.comparisons,
+            counters.swaps,
+            counters.elapsed.as_secs_f32(),
+        );
+        if let Some(rank) = rank {
+            text.push_str(&format!("\nFinished: {}", ordinal(rank)));
+        }
+        let height = LINE_HEIGHT * 5.0;
+
+        draw.text(&text)
+            .x_y(0.0, pane_dims.1 / 2.0 - height / 2.0 - MARGIN)
+            .w_h(pane_dims.0 - 2.0 * MARGIN, height)
+            .left_justify()
+            .font_size(12)
+            .color(WHITE);
+    }
+}
+
+// Columns and rows for a number of panes, with at least as many columns as rows since windows are usually wide.
+fn grid(panes: usize) -> (usize, usize) {
+    let cols = (panes as f32).sqrt().ceil() as usize;
+    (cols, (panes + cols - 1) / cols)
+}
+
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::Race;
+    use crate::{
+        raster::Raster,
+        sorting_array::{DisplayMode, ShuffleType, SortArray, distribution::Distribution, tests::registry},
+    };
+
+    #[test]
+    fn race_panes_in_circle_mode_are_drawn_in_their_own_panes() {
+        const WINDOW: (usize, usize) = (60, 20);
+        let sorts = Arc::new(registry());
+        let race = Race::new((0..12).collect(), vec![0, 1]);
+        let window_dims = (WINDOW.0 as f32, WINDOW.1 as f32);
+
+        for pane in 0..race.panes() {
+            // Only the pane being checked has any elements.
+            let arrays: Vec<SortArray> = (0..race.panes())
+                .map(|i| {
+                    let mut array = SortArray::new(0, Arc::clone(&sorts), Distribution::Sorted, ShuffleType::FisherYates, Duration::default(), 0);
+                    if i == pane {
+                        array.set_values(race.input().to_vec());
+                    }
+                    array
+                })
+                .collect();
+
+            let mut raster = Raster::new(WINDOW.0, WINDOW.1);
+            race.draw(&mut raster, &arrays, DisplayMode::Circle, window_dims, 0.5);
+
+            let (x, y, w, h) = race.pane_rect(pane, window_dims);
+            let left = x - w / 2.0 + window_dims.0 / 2.0;
+            let top = window_dims.1 / 2.0 - y - h / 2.0;
+            let mut drawn = 0;
+
+            for row in 0..WINDOW.1 {
+                for column in 0..WINDOW.0 {
+                    let [r, g, b, _] = raster.pixel(column, row);
+                    if r == g && g == b {
+                        continue;   // Background or pane outline
+                    }
+                    drawn += 1;
+
+                    let (px, py) = (column as f32 + 0.5, row as f32 + 0.5);
+                    assert!(
+                        px >= left && px <= left + w && py >= top && py <= top + h,
+                        "pane {} drew at ({}, {}), outside its pane", pane, column, row,
+                    );
+                }
+            }
+            assert!(drawn > 0, "pane {} drew nothing", pane);
+        }
+    }
+}
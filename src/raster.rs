@@ -0,0 +1,194 @@
+// Software rasteriser, for drawing arrays without a GPU (e.g. when exporting animations). Every shape is filled as a
+// convex polygon a row of pixels at a time, with no anti-aliasing. Pixels are 8 bit RGBA, from the top left.
+
+use crate::canvas::{Canvas, Colour, Point};
+use crate::TWO_PI;
+
+const SMOOTH_RESOLUTION: usize = 64;    // Sides of ellipses drawn with no resolution
+
+pub struct Raster {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Raster {
+    pub fn new(width: usize, height: usize) -> Self {
+        let mut raster = Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 4],
+        };
+        raster.clear(Colour::BLACK);
+        raster
+    }
+
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    #[inline]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn clear(&mut self, colour: Colour) {
+        let pixel = to_bytes(colour);
+        for chunk in self.pixels.chunks_exact_mut(4) {
+            chunk.copy_from_slice(&pixel);
+        }
+    }
+
+    // RGBA of the pixel at (x, y) from the top left.
+    #[cfg(test)]
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * self.width + x) * 4;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    fn blend(&mut self, x: usize, y: usize, colour: Colour) {
+        let i = (y * self.width + x) * 4;
+        let source = to_bytes(colour);
+        let alpha = colour.a.clamp(0.0, 1.0);
+
+        for (pixel, &source) in self.pixels[i..i + 3].iter_mut().zip(&source) {
+            *pixel = (source as f32 * alpha + *pixel as f32 * (1.0 - alpha)).round() as u8;
+        }
+        self.pixels[i + 3] = 255;
+    }
+
+    // Fills the pixels whose centres are inside the polygon. The points can go either way around.
+    fn fill_convex(&mut self, points: &[Point], colour: Colour) {
+        // Pixel coordinates, with y going down from the top left.
+        let points: Vec<(f32, f32)> = points.iter()
+            .map(|point| (point[0] + self.width as f32 / 2.0, self.height as f32 / 2.0 - point[1]))
+            .collect();
+
+        let min_y = points.iter().map(|p| p.1).fold(f32::INFINITY, f32::min);
+        let max_y = points.iter().map(|p| p.1).fold(f32::NEG_INFINITY, f32::max);
+        let first_row = (min_y - 0.5).ceil().max(0.0) as usize;
+        let last_row = ((max_y - 0.5).floor().min(self.height as f32 - 1.0)).max(-1.0);
+
+        if last_row < 0.0 {
+            return;
+        }
+
+        for row in first_row..=last_row as usize {
+            let centre_y = row as f32 + 0.5;
+            let mut left = f32::INFINITY;
+            let mut right = f32::NEG_INFINITY;
+
+            for (i, &(x1, y1)) in points.iter().enumerate() {
+                let (x2, y2) = points[(i + 1) % points.len()];
+                if (y1 <= centre_y && centre_y < y2) || (y2 <= centre_y && centre_y < y1) {
+                    let x = x1 + (centre_y - y1) / (y2 - y1) * (x2 - x1);
+                    left = left.min(x);
+                    right = right.max(x);
+                }
+            }
+
+            let first_column = (left - 0.5).ceil().max(0.0);
+            let end_column = (right - 0.5).ceil().min(self.width as f32);
+            if first_column < end_column {
+                for column in first_column as usize..end_column as usize {
+                    self.blend(column, row, colour);
+                }
+            }
+        }
+    }
+}
+
+#[inline]
+fn to_bytes(colour: Colour) -> [u8; 4] {
+    let byte = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [byte(colour.r), byte(colour.g), byte(colour.b), byte(colour.a)]
+}
+
+#[inline]
+fn rect_points(centre: Point, dims: (f32, f32)) -> [Point; 4] {
+    let (half_w, half_h) = (dims.0 / 2.0, dims.1 / 2.0);
+    [
+        [centre[0] - half_w, centre[1] - half_h],
+        [centre[0] + half_w, centre[1] - half_h],
+        [centre[0] + half_w, centre[1] + half_h],
+        [centre[0] - half_w, centre[1] + half_h],
+    ]
+}
+
+impl Canvas for Raster {
+    fn line(&mut self, start: Point, end: Point, weight: f32, colour: Colour) {
+        let (dx, dy) = (end[0] - start[0], end[1] - start[1]);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 {
+            return;
+        }
+
+        // Perpendicular to the line, half the weight long.
+        let (nx, ny) = (-dy / len * weight / 2.0, dx / len * weight / 2.0);
+        self.fill_convex(&[
+            [start[0] + nx, start[1] + ny],
+            [end[0] + nx, end[1] + ny],
+            [end[0] - nx, end[1] - ny],
+            [start[0] - nx, start[1] - ny],
+        ], colour);
+    }
+
+    fn rect(&mut self, centre: Point, dims: (f32, f32), colour: Colour) {
+        self.fill_convex(&rect_points(centre, dims), colour);
+    }
+
+    fn rect_outline(&mut self, centre: Point, dims: (f32, f32), weight: f32, colour: Colour) {
+        let (half_w, half_h) = (dims.0 / 2.0, dims.1 / 2.0);
+        let (outer_w, outer_h) = (dims.0 + weight, dims.1 + weight);
+
+        self.rect([centre[0], centre[1] + half_h], (outer_w, weight), colour);
+        self.rect([centre[0], centre[1] - half_h], (outer_w, weight), colour);
+        self.rect([centre[0] - half_w, centre[1]], (weight, outer_h - 2.0 * weight), colour);
+        self.rect([centre[0] + half_w, centre[1]], (weight, outer_h - 2.0 * weight), colour);
+    }
+
+    fn tri(&mut self, points: [Point; 3], colour: Colour) {
+        self.fill_convex(&points, colour);
+    }
+
+    fn quad(&mut self, points: [Point; 4], colour: Colour) {
+        self.fill_convex(&points, colour);
+    }
+
+    fn ellipse(&mut self, centre: Point, radius: f32, resolution: Option<usize>, colour: Colour) {
+        let sides = resolution.unwrap_or(SMOOTH_RESOLUTION).max(3);
+        let points: Vec<Point> = (0..sides)
+            .map(|i| {
+                let angle = i as f32 / sides as f32 * TWO_PI;
+                [centre[0] + radius * angle.cos(), centre[1] + radius * angle.sin()]
+            })
+            .collect();
+        self.fill_convex(&points, colour);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Raster;
+    use crate::sorting_array::{DataArrWrapper, DisplayMode};
+
+    #[test]
+    fn bars_rasterise_one_column_per_element_scaled_to_the_height() {
+        let data = DataArrWrapper::new(vec![0, 1, 2, 3], 4);
+        let mut raster = Raster::new(4, 4);
+        data.draw(&mut raster, 0, 1, data.len(), DisplayMode::Bars, (4.0, 4.0), (-2.0, -2.0), 0.5);
+
+        for column in 0..4 {
+            for row in 0..4 {
+                let filled = raster.pixel(column, row)[..3] != [0, 0, 0];
+                assert_eq!(filled, 3 - row <= column, "pixel ({}, {})", column, row);
+            }
+        }
+    }
+}
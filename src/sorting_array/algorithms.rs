@@ -0,0 +1,602 @@
+use std::convert::TryFrom;
+use std::ops::Index;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use yaml_rust::Yaml;
+
+use super::{
+    DataArrWrapper,
+    commands::*,
+    context::{SortContext, SortCounters, SortResult, CounterSnapshot},
+    distribution,
+    pool::SortPool,
+    random::{self, SortRng},
+    sorts,
+};
+
+// Time complexity class of a sort, used to spread the sort's sleep time over the operations it is expected to do.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Complexity {
+    NSquared,       // O(n^2)
+    NPow3Over2,     // O[n^(3/2)]
+    NLogN,          // O[n log n]. Applied as O(n), since log n makes little difference.
+    NW,             // O[w * n], where w is the bits required to store each key (Radix Sorts). Applied as O(n)
+}
+
+impl Complexity {
+    // Number that the sleep time from the config is divided by for an array of this length. Capped at u32::MAX, where
+    // the sleep time is as good as nothing anyway.
+    pub fn divisor(&self, len: usize) -> u32 {
+        let len = len as u64;
+        let divisor = match self {
+            Complexity::NSquared => len.saturating_mul(len),
+            Complexity::NPow3Over2 => (len as f64).powf(3.0/2.0).floor() as u64,
+            Complexity::NLogN | Complexity::NW => len,
+        };
+        u32::try_from(divisor).unwrap_or(u32::MAX).max(1)
+    }
+}
+
+// A sort that can be run on a SortArray. To add a new sort, implement this and add it to `SortRegistry::from`.
+pub trait SortAlgorithm: Send + Sync {
+    // Shown in menus and on screen.
+    fn name(&self) -> &'static str;
+
+    // Used for config keys, e.g "bubble" reads its sleep time from "bubble_sleep".
+    fn id(&self) -> &'static str;
+
+    // Key that starts the sort.
+    fn key(&self) -> char;
+
+    fn complexity(&self) -> Complexity;
+
+    // Reads the sort's options from the config file. Panics if they are invalid, like the rest of the config.
+    fn configure(&mut self, _conf: &Yaml) {}
+
+    // Description of the currently configured options, if the sort has any.
+    fn options(&self) -> Option<String> {
+        None
+    }
+
+    // The name followed by the options in brackets, if there are any. Tells variants of a sort apart.
+    fn full_name(&self) -> String {
+        match self.options() {
+            Some(options) => format!("{} ({})", self.name(), options),
+            None => self.name().to_string(),
+        }
+    }
+
+    // A copy of the sort for every combination of its options, so they can be compared in benchmarks.
+    // Empty if the sort has no options.
+    fn variants(&self) -> Vec<Box<dyn SortAlgorithm>> {
+        Vec::new()
+    }
+
+    fn run(&self, ctx: &SortContext) -> SortResult;
+}
+
+// Runs a sort on the current thread (and the registry's pool, if it is multithreaded) with no sleeping and no window.
+// Returns the sorted values and the counters.
+pub fn run_headless(algorithm: &dyn SortAlgorithm, values: Vec<usize>, sorts: &SortRegistry) -> (Vec<usize>, CounterSnapshot) {
+    let max_val = distribution::max_value(&values);
+    let (data, counters) = run_wrapper(algorithm, DataArrWrapper::new(values, max_val), sorts);
+    (data.arr, counters)
+}
+
+// Like run_headless, but records a trace of the sort. Returns the array as the sort left it, with the trace in it, so
+// that the trace can be played back (or rewound) from there.
+pub fn run_headless_traced(algorithm: &dyn SortAlgorithm, values: Vec<usize>, sorts: &SortRegistry) -> DataArrWrapper {
+    let max_val = distribution::max_value(&values);
+    let mut wrapper = DataArrWrapper::new(values, max_val);
+    wrapper.trace = Some(Vec::new());
+    run_wrapper(algorithm, wrapper, sorts).0
+}
+
+fn run_wrapper(algorithm: &dyn SortAlgorithm, mut wrapper: DataArrWrapper, sorts: &SortRegistry) -> (DataArrWrapper, CounterSnapshot) {
+    wrapper.sorted = false; // Mark as running, otherwise the context reports that the sort has been stopped.
+
+    let data = Arc::new(RwLock::new(wrapper));
+    let counters = Arc::new(SortCounters::default());
+    let ctx = SortContext::new(
+        Arc::clone(&data),
+        Arc::clone(&counters),
+        Duration::default(),
+        Arc::clone(&sorts.pool),
+        Arc::clone(&sorts.rng),
+    );
+
+    counters.start();
+    let _ = algorithm.run(&ctx);    // Nothing can stop a headless sort
+    counters.finish();
+
+    let mut write = data.write().unwrap();
+    write.sorted = true;
+    let wrapper = std::mem::replace(&mut *write, DataArrWrapper::new(Vec::new(), 0));
+    (wrapper, counters.snapshot())
+}
+
+pub struct RegisteredSort {
+    pub algorithm: Box<dyn SortAlgorithm>,
+    pub sleep_time: Duration,
+}
+
+impl RegisteredSort {
+    // Sleep time for each operation when sorting an array of this length.
+    #[inline]
+    pub fn sleep_time_for(&self, len: usize) -> Duration {
+        self.sleep_time / self.algorithm.complexity().divisor(len)
+    }
+}
+
+// All of the sorts available, in the order they are listed in menus. Sorts are referred to by their index.
+pub struct SortRegistry {
+    sorts: Vec<RegisteredSort>,
+    pub pool: Arc<SortPool>,    // For headless runs. Each array starts its own like it, see SortArray::pool
+    pub rng: Arc<SortRng>,      // Shared by every array
+}
+
+impl SortRegistry {
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &RegisteredSort> {
+        self.sorts.iter()
+    }
+
+    // "<key>: <name> (<options>)" for each sort, for help text.
+    pub fn key_list(&self) -> Vec<String> {
+        self.sorts.iter()
+            .map(|sort| format!("{}: {}", sort.algorithm.key(), sort.algorithm.full_name()))
+            .collect()
+    }
+
+    // Index of the sort bound to this key.
+    pub fn find_by_key(&self, key: char) -> Option<usize> {
+        self.sorts.iter().position(|sort| sort.algorithm.key() == key)
+    }
+}
+
+impl Index<usize> for SortRegistry {
+    type Output = RegisteredSort;
+
+    #[inline]
+    fn index(&self, index: usize) -> &RegisteredSort {
+        &self.sorts[index]
+    }
+}
+
+impl From<&Yaml> for SortRegistry {
+    fn from(conf: &Yaml) -> Self {
+        let algorithms: Vec<Box<dyn SortAlgorithm>> = vec![
+            Box::new(BubbleSort),
+            Box::new(CocktailShakerSort),
+            Box::new(InsertionSort),
+            Box::new(SelectionSort),
+            Box::new(ShellSort),
+            Box::new(QuickSort::default()),
+            Box::new(MergeSort::default()),
+            Box::new(TimSort),
+            Box::new(HeapSort::default()),
+            Box::new(RadixSort::default()),
+        ];
+
+        let sorts = algorithms.into_iter()
+            .map(|mut algorithm| {
+                algorithm.configure(conf);
+
+                let sleep_name = format!("{}_sleep", algorithm.id());
+                let yaml_field = &conf[sleep_name.as_str()];
+                let sleep_time = Duration::from_millis(
+                    yaml_field.as_i64()
+                        .unwrap_or_else(|| panic!("Could not parse {} as an integer: {:?}", sleep_name, yaml_field)) as u64
+                );
+
+                RegisteredSort { algorithm, sleep_time }
+            })
+            .collect();
+
+        let pool = SortPool::new(
+            conf["sort_workers"].as_i64()
+                .expect("Could not parse sort_workers field in config as an integer.") as usize,
+            conf["parallel_cutoff_depth"].as_i64()
+                .expect("Could not parse parallel_cutoff_depth field in config as an integer.") as usize,
+        );
+
+        // A number, or anything else (e.g. "random") for a new seed every time.
+        let seed = match conf["seed"].as_i64() {
+            Some(seed) if seed < 0 => panic!("Seed in config should be 0 or more (or random), not {}.", seed),
+            Some(seed) => seed as u64,
+            None => random::random_seed(),
+        };
+
+        Self { sorts, pool: Arc::new(pool), rng: Arc::new(SortRng::new(seed)) }
+    }
+}
+
+pub struct BubbleSort;
+
+impl SortAlgorithm for BubbleSort {
+    fn name(&self) -> &'static str {
+        "Bubble Sort"
+    }
+
+    fn id(&self) -> &'static str {
+        "bubble"
+    }
+
+    fn key(&self) -> char {
+        '1'
+    }
+
+    fn complexity(&self) -> Complexity {
+        Complexity::NSquared
+    }
+
+    fn run(&self, ctx: &SortContext) -> SortResult {
+        sorts::bubble_sort(ctx)
+    }
+}
+
+pub struct CocktailShakerSort;
+
+impl SortAlgorithm for CocktailShakerSort {
+    fn name(&self) -> &'static str {
+        "Cocktail Shaker Sort"
+    }
+
+    fn id(&self) -> &'static str {
+        "cocktail_shaker"
+    }
+
+    fn key(&self) -> char {
+        '2'
+    }
+
+    fn complexity(&self) -> Complexity {
+        Complexity::NSquared
+    }
+
+    fn run(&self, ctx: &SortContext) -> SortResult {
+        sorts::cocktail_shaker_sort(ctx)
+    }
+}
+
+pub struct InsertionSort;
+
+impl SortAlgorithm for InsertionSort {
+    fn name(&self) -> &'static str {
+        "Insertion Sort"
+    }
+
+    fn id(&self) -> &'static str {
+        "insertion"
+    }
+
+    fn key(&self) -> char {
+        '3'
+    }
+
+    fn complexity(&self) -> Complexity {
+        Complexity::NSquared
+    }
+
+    fn run(&self, ctx: &SortContext) -> SortResult {
+        sorts::insertion_sort(ctx, 0, ctx.len().saturating_sub(1))
+    }
+}
+
+pub struct SelectionSort;
+
+impl SortAlgorithm for SelectionSort {
+    fn name(&self) -> &'static str {
+        "Selection Sort"
+    }
+
+    fn id(&self) -> &'static str {
+        "selection"
+    }
+
+    fn key(&self) -> char {
+        '4'
+    }
+
+    fn complexity(&self) -> Complexity {
+        Complexity::NSquared
+    }
+
+    fn run(&self, ctx: &SortContext) -> SortResult {
+        sorts::selection_sort(ctx)
+    }
+}
+
+pub struct ShellSort;
+
+impl SortAlgorithm for ShellSort {
+    fn name(&self) -> &'static str {
+        "Shell Sort"
+    }
+
+    fn id(&self) -> &'static str {
+        "shell"
+    }
+
+    fn key(&self) -> char {
+        '5'
+    }
+
+    fn complexity(&self) -> Complexity {
+        Complexity::NPow3Over2
+    }
+
+    fn run(&self, ctx: &SortContext) -> SortResult {
+        sorts::shell_sort(ctx)
+    }
+}
+
+pub struct QuickSort {
+    pub quick_sort_type: QuickSortType,
+}
+
+impl Default for QuickSort {
+    fn default() -> Self {
+        Self {
+            quick_sort_type: QuickSortType {
+                partitioning: Partitioning::Lomuto,
+                pivot: PivotSelection::Last,
+                multithreaded: false,
+                insertion_hybrid: false,
+            },
+        }
+    }
+}
+
+impl SortAlgorithm for QuickSort {
+    fn name(&self) -> &'static str {
+        "Quicksort"
+    }
+
+    fn id(&self) -> &'static str {
+        "quick"
+    }
+
+    fn key(&self) -> char {
+        '6'
+    }
+
+    fn complexity(&self) -> Complexity {
+        Complexity::NLogN
+    }
+
+    fn configure(&mut self, conf: &Yaml) {
+        self.quick_sort_type = QuickSortType::from_str(
+            conf["quicksort_partitioning"].as_str().expect("Could not parse quicksort_partitioning field in config as a string.")
+        ).unwrap();
+    }
+
+    fn options(&self) -> Option<String> {
+        let QuickSortType { partitioning, pivot, multithreaded, insertion_hybrid } = self.quick_sort_type;
+        Some(format!(
+            "{:?}, {} pivot{}{}",
+            partitioning,
+            pivot.name().replace('_', " "),
+            if insertion_hybrid { ", insertion hybrid" } else { "" },
+            if multithreaded { ", multithreaded" } else { "" },
+        ))
+    }
+
+    fn variants(&self) -> Vec<Box<dyn SortAlgorithm>> {
+        let mut variants: Vec<Box<dyn SortAlgorithm>> = Vec::new();
+        for &partitioning in &[Partitioning::Lomuto, Partitioning::Hoare] {
+            for &pivot in ALL_PIVOT_SELECTIONS.iter() {
+                for &multithreaded in &[false, true] {
+                    for &insertion_hybrid in &[false, true] {
+                        variants.push(Box::new(QuickSort {
+                            quick_sort_type: QuickSortType { partitioning, pivot, multithreaded, insertion_hybrid },
+                        }));
+                    }
+                }
+            }
+        }
+        variants
+    }
+
+    fn run(&self, ctx: &SortContext) -> SortResult {
+        let r = ctx.len().saturating_sub(1);   // Empty arrays are left as they are, since l == r
+        let QuickSortType { partitioning, pivot, multithreaded, insertion_hybrid } = self.quick_sort_type;
+
+        if multithreaded && insertion_hybrid {
+            ctx.pool().install(|| sorts::quick_sorting::quicktimsort_multithreaded(ctx, 0, r, partitioning, pivot, 0))
+        } else if multithreaded {
+            ctx.pool().install(|| sorts::quick_sorting::quick_sort_multithreaded(ctx, 0, r, partitioning, pivot, 0))
+        } else if insertion_hybrid {
+            sorts::quick_sorting::quicktimsort(ctx, 0, r, partitioning, pivot)
+        } else {
+            sorts::quick_sorting::quick_sort(ctx, 0, r, partitioning, pivot)
+        }
+    }
+}
+
+pub struct MergeSort {
+    pub merge_type: MergeSortType,
+}
+
+impl Default for MergeSort {
+    fn default() -> Self {
+        Self {
+            merge_type: MergeSortType::InPlace { multithreaded: false },
+        }
+    }
+}
+
+impl SortAlgorithm for MergeSort {
+    fn name(&self) -> &'static str {
+        "Merge Sort"
+    }
+
+    fn id(&self) -> &'static str {
+        "merge"
+    }
+
+    fn key(&self) -> char {
+        '7'
+    }
+
+    fn complexity(&self) -> Complexity {
+        Complexity::NLogN
+    }
+
+    fn configure(&mut self, conf: &Yaml) {
+        self.merge_type = MergeSortType::from_str(
+            conf["merge_sort_type"].as_str().expect("Could not parse merge_sort_type field in config as a string.")
+        ).unwrap();
+    }
+
+    fn options(&self) -> Option<String> {
+        match self.merge_type {
+            MergeSortType::InPlace { multithreaded } => Some(format!(
+                "In-place{}",
+                if multithreaded { ", multithreaded" } else { "" },
+            )),
+        }
+    }
+
+    fn variants(&self) -> Vec<Box<dyn SortAlgorithm>> {
+        vec![
+            Box::new(MergeSort { merge_type: MergeSortType::InPlace { multithreaded: false } }),
+            Box::new(MergeSort { merge_type: MergeSortType::InPlace { multithreaded: true } }),
+        ]
+    }
+
+    fn run(&self, ctx: &SortContext) -> SortResult {
+        let r = ctx.len().saturating_sub(1);
+
+        match self.merge_type {
+            MergeSortType::InPlace {
+                multithreaded,
+            } => {
+                if multithreaded {
+                    ctx.pool().install(|| sorts::merge_sorting::merge_sort_in_place_multithreaded(ctx, 0, r, 0))
+                } else {
+                    sorts::merge_sorting::merge_sort_in_place(ctx, 0, r)
+                }
+            }
+        }
+    }
+}
+
+pub struct TimSort;
+
+impl SortAlgorithm for TimSort {
+    fn name(&self) -> &'static str {
+        "TimSort"
+    }
+
+    fn id(&self) -> &'static str {
+        "tim"
+    }
+
+    fn key(&self) -> char {
+        '8'
+    }
+
+    // Runs are merged in place, like merge sort.
+    fn complexity(&self) -> Complexity {
+        Complexity::NSquared
+    }
+
+    fn run(&self, ctx: &SortContext) -> SortResult {
+        sorts::tim_sorting::tim_sort(ctx)
+    }
+}
+
+pub struct HeapSort {
+    pub heap_type: HeapSortType,
+}
+
+impl Default for HeapSort {
+    fn default() -> Self {
+        Self {
+            heap_type: HeapSortType::Binary,
+        }
+    }
+}
+
+impl SortAlgorithm for HeapSort {
+    fn name(&self) -> &'static str {
+        "Heap Sort"
+    }
+
+    fn id(&self) -> &'static str {
+        "heap"
+    }
+
+    fn key(&self) -> char {
+        '0'
+    }
+
+    fn complexity(&self) -> Complexity {
+        Complexity::NLogN
+    }
+
+    fn configure(&mut self, conf: &Yaml) {
+        self.heap_type = HeapSortType::from_str(
+            conf["heap_sort_type"].as_str().expect("Could not parse heap_sort_type field in config as a string.")
+        ).unwrap();
+    }
+
+    fn options(&self) -> Option<String> {
+        Some(format!("{:?}", self.heap_type))
+    }
+
+    fn variants(&self) -> Vec<Box<dyn SortAlgorithm>> {
+        vec![
+            Box::new(HeapSort { heap_type: HeapSortType::Binary }),
+            Box::new(HeapSort { heap_type: HeapSortType::Ternary }),
+        ]
+    }
+
+    fn run(&self, ctx: &SortContext) -> SortResult {
+        sorts::heap_sort(ctx, self.heap_type.arity())
+    }
+}
+
+pub struct RadixSort {
+    pub base: usize,
+}
+
+impl Default for RadixSort {
+    fn default() -> Self {
+        Self { base: 10 }
+    }
+}
+
+impl SortAlgorithm for RadixSort {
+    fn name(&self) -> &'static str {
+        "Radix LSD Sort"
+    }
+
+    fn id(&self) -> &'static str {
+        "radix"
+    }
+
+    fn key(&self) -> char {
+        '9'
+    }
+
+    fn complexity(&self) -> Complexity {
+        Complexity::NW
+    }
+
+    fn configure(&mut self, conf: &Yaml) {
+        self.base = conf["radix_lsd_base"].as_i64()
+            .expect("Could not parse radix_lsd_base as an integer.") as usize;
+    }
+
+    fn options(&self) -> Option<String> {
+        Some(format!("Base {}", self.base))
+    }
+
+    fn run(&self, ctx: &SortContext) -> SortResult {
+        sorts::radix_lsd(ctx, self.base)
+    }
+}
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::sync::{Arc, RwLock};
 use std::thread;
 use std::time::Duration;
@@ -5,34 +6,61 @@ use std::time::Duration;
 use nannou::{
     draw::Draw,
     geom::point::Point2,
-    color::{
-        named::*,
-        LinSrgba,
-    },
+    color::named::*,
 };
 
-use super::{commands::*, sorts};
-use crate::{tools, TWO_PI, config::SleepTimes};
+use super::{
+    commands::*,
+    algorithms::{Complexity, SortRegistry},
+    context::{SortContext, SortCounters, CounterSnapshot},
+    distribution::{self, Distribution},
+    file::LoadedArray,
+    highlight::{self, DrawnRange, Highlight, Highlights, Mark},
+    pool::{self, SortPool},
+    shuffles,
+    trace::{HeapShape, Operation},
+};
+use crate::{
+    canvas::{Canvas, Colour, DrawCanvas, Point},
+    tools, TWO_PI,
+};
+
+const PAUSE_POLL_TIME: Duration = Duration::from_millis(10);   // How often a paused sort checks whether it can continue.
+const RANGE_STRIP_WIDTH: f32 = 4.0;     // Width of the strips drawn alongside highlighted ranges
 
 macro_rules! start_sort_thread {
-    // Starts a sorting thread (common pattern)
-    ($self:expr, $data_arc:expr, $operation:block) => {
-        $data_arc.write().unwrap().sorted = false;
+    // Starts a sorting thread (common pattern). The operation is given a context that uses the counters and sleep time.
+    ($self:expr, $counters:expr, $sleep_time:expr, |$ctx:ident| $operation:expr) => {
+        {
+            let mut write = $self.data.write().unwrap();
+            write.sorted = false;
+            // Start recording a new trace if recording is on, dropping the last one either way
+            write.trace = if write.trace_limit > 0 { Some(Vec::new()) } else { None };
+        }
+        let data_arc = Arc::clone(&$self.data);
+        let counters: Arc<SortCounters> = $counters;
+        counters.start();
+        let $ctx = SortContext::new(
+            Arc::clone(&data_arc),
+            Arc::clone(&counters),
+            $sleep_time,
+            $self.pool(),
+            Arc::clone(&$self.sorts.rng),
+        );
         $self.sort_thread = Some(thread::spawn(move || {
-            $operation;
-            SortArray::reset_arr_info($data_arc);
+            let _ = $operation;     // If the sort was stopped it just ends early
+            counters.finish();
+            SortArray::reset_arr_info(data_arc);
         }));
     };
 }
 
-// Colour the element when using red -> green colours (uses purple and blues to display pivot etc).
-// Used in both dots and bars vis.
+// Colour the element when using red -> green colours (uses the colours of any markers on it instead, e.g. purple and
+// blues to display pivot etc). Used in both dots and bars vis.
 macro_rules! colour_element_red_grn_clrs {
     ($data_read:expr, $i:expr, $drawing:expr, $max_val:expr, $d:expr) => {
-        if Some($i) == $data_read.active || Some($i) == $data_read.active_2 {
-            $drawing.rgb(0.0, 0.2, 1.0);
-        } else if Some($i) == $data_read.pivot {
-            $drawing.rgb(0.8516, 0.4023, 0.8945); // Purple colour
+        if let Some((r, g, b)) = $data_read.highlights.colour($i) {
+            $drawing.rgb(r, g, b);
         } else {
             $drawing.hsv((*$d as f32 / $max_val as f32) / 3.0, 1.0, 1.0);
         }
@@ -45,172 +73,225 @@ pub struct DataArrWrapper {
     // Wrapper arround array that is passed to sorting thread, containing info about current sort.
     #[shrinkwrap(main_field)]
     pub arr: Vec<usize>,
-    pub active: Option<usize>,
-    pub active_2: Option<usize>,
-    pub pivot: Option<usize>,
+    pub highlights: Highlights,
+    pub heap: Option<HeapShape>,    // Set while a heap sort is running
     pub should_play_sound: bool,
     pub sorted: bool,
+    pub paused: bool,
+    pub steps: usize,   // Number of checkpoints a paused sort is allowed to pass.
     pub max_val: usize,
+    pub trace: Option<Vec<Operation>>,    // Operations recorded for replay. Only recorded when Some.
+    pub trace_limit: usize,     // Most operations a trace can have. Longer traces are dropped, and 0 turns recording off.
 }
 
 impl DataArrWrapper {
     pub fn new(arr: Vec<usize>, max_val: usize) -> Self {
         Self {
             arr,
-            active: None,
-            active_2: None,
-            pivot: None,
+            highlights: Highlights::default(),
+            heap: None,
             should_play_sound: false,
             sorted: true,
+            paused: false,
+            steps: 0,
             max_val,
+            trace: None,
+            trace_limit: usize::MAX,
         }
     }
 
     #[inline]
-    pub fn set_active(&mut self, index: usize) {
-        self.active = Some(index);
-        self.should_play_sound = true;
+    fn record(&mut self, operation: Operation) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(operation);
+            // A replay has to start from the start of the trace, so one that gets too long is dropped rather than cut.
+            if trace.len() > self.trace_limit {
+                self.trace = None;
+            }
+        }
     }
 
-    #[inline]
-    pub fn set_active_2(&mut self, index: usize) {
-        self.active_2 = Some(index);
+    // Moves (or removes) the current thread's marker for a highlight, recording the change.
+    pub fn set_marker(&mut self, highlight: Highlight, index: Option<usize>) {
+        let owner = pool::highlight_owner();
+        let old = self.highlights.marker(owner, highlight).map(Mark::Marker);
+        let new = index.map(Mark::Marker);
+        if old != new {
+            self.change_highlight(owner, highlight, old, new);
+            self.record(Operation::Highlight { owner, highlight, old, new });
+        }
     }
 
-    #[inline]
-    pub fn set_pivot(&mut self, index: usize) {
-        self.pivot = Some(index);
+    // Highlights or un-highlights a range for the current thread, recording the change.
+    pub fn highlight_range(&mut self, highlight: Highlight, start: usize, len: usize, added: bool) {
+        let owner = pool::highlight_owner();
+        let range = Some(Mark::Range { start, len });
+        let (old, new) = if added { (None, range) } else { (range, None) };
+        self.change_highlight(owner, highlight, old, new);
+        self.record(Operation::Highlight { owner, highlight, old, new });
     }
-}
 
-pub struct SortArray {
-    pub data: Arc<RwLock<DataArrWrapper>>,
-    sleep_times: Arc<SleepTimes>,
-    sort_thread: Option<thread::JoinHandle<()>>,
-}
+    // Removes all of the current thread's marks for a highlight, recording each one.
+    pub fn clear_highlight(&mut self, highlight: Highlight) {
+        let owner = pool::highlight_owner();
+        for mark in self.highlights.marks(owner, highlight).to_vec() {
+            self.change_highlight(owner, highlight, Some(mark), None);
+            self.record(Operation::Highlight { owner, highlight, old: Some(mark), new: None });
+        }
+    }
 
-impl SortArray {
-    pub fn new(num_of_lines: usize, sleep_times: Arc<SleepTimes>) -> SortArray {
-        SortArray {
-            data: Arc::new(RwLock::new(
-                DataArrWrapper::new(
-                    (0..num_of_lines).collect(), // Make an array of incrementing numbers up to the length of the array.
-                    num_of_lines,
-                ),
-            )), // Then when drawing you can scale it however you want.
-            sleep_times,
-            sort_thread: None,
+    // Removes every thread's marks, recording each one.
+    pub fn clear_highlights(&mut self) {
+        let marks: Vec<_> = self.highlights.sets().iter()
+            .flat_map(|set| set.marks.iter().map(move |&mark| (set.owner, set.highlight, mark)))
+            .collect();
+        for (owner, highlight, mark) in marks {
+            self.highlights.change(owner, highlight, Some(mark), None);
+            self.record(Operation::Highlight { owner, highlight, old: Some(mark), new: None });
         }
     }
 
-    // Easier to handle in here rather than in main
-    pub fn instruction(&mut self, instruction: SortInstruction) {
-        let data_arc_cln = Arc::clone(&self.data);
-        let sleep_times_cln = Arc::clone(&self.sleep_times);
-        let data_len = data_arc_cln.read().unwrap().len();
+    // Changes a highlight without recording it.
+    pub(super) fn change_highlight(&mut self, owner: usize, highlight: Highlight, old: Option<Mark>, new: Option<Mark>) {
+        if (highlight == highlight::ACTIVE || highlight == highlight::ACTIVE_2) && new.is_some() {
+            self.should_play_sound = true;
+        }
+        self.highlights.change(owner, highlight, old, new);
+    }
 
-        match instruction {
-            SortInstruction::Shuffle(rounds) => {
-                start_sort_thread!(self, data_arc_cln, {
-                    let sleep_time = sleep_times_cln.shuffle/data_len.pow(2) as u32;
-                    Self::shuffle(data_arc_cln.clone(), &sleep_time, rounds);
-                });
-            }
-            SortInstruction::BubbleSort => {
-                start_sort_thread!(self, data_arc_cln, {
-                    let sleep_time = sleep_times_cln.bubble/data_len.pow(2) as u32;
-                    sorts::bubble_sort(data_arc_cln.clone(), &sleep_time);
-                });
-            }
-            SortInstruction::QuickSort(partition_type) => {
-                start_sort_thread!(self, data_arc_cln, {
-                    let sleep_time = Arc::new(sleep_times_cln.quick/data_len as u32); //sleep_times_cln.quick/((data_len as f32).log10().floor() as u32 * data_len as u32);
-                    match partition_type {
-                        QuickSortType::Lomuto {
-                            multithreaded,
-                            insertion_hybrid,
-                        } => {
-                            if multithreaded && insertion_hybrid {
-                                sorts::quick_sorting::quicktimsort_multithreaded(data_arc_cln.clone(), sleep_time, 0, data_len - 1)
-                            } else if multithreaded {
-                                sorts::quick_sorting::quick_sort_lomuto_multithreaded(data_arc_cln.clone(), sleep_time, 0, data_len - 1)
-                            } else if insertion_hybrid {
-                                sorts::quick_sorting::quicktimsort(data_arc_cln.clone(), sleep_time, 0, data_len - 1)
-                            } else {
-                                sorts::quick_sorting::quick_sort_lomuto(data_arc_cln.clone(), sleep_time, 0, data_len - 1)
-                            }
-                        }
-                    }
-                });
-            }
-            SortInstruction::InsertionSort => {
-                start_sort_thread!(self, data_arc_cln, {
-                    let sleep_time = sleep_times_cln.insertion/(data_len).pow(2) as u32;
-                    sorts::insertion_sort(data_arc_cln.clone(), &sleep_time, 0, data_len - 1);
-                });
-            }
-            SortInstruction::SelectionSort => {
-                start_sort_thread!(self, data_arc_cln, {
-                    let sleep_time = sleep_times_cln.selection/(data_len).pow(2) as u32;
-                    sorts::selection_sort(data_arc_cln.clone(), &sleep_time);
-                });
-            }
-            SortInstruction::CocktailShakerSort => {
-                start_sort_thread!(self, data_arc_cln, {
-                    let sleep_time = sleep_times_cln.cocktail/(data_len).pow(2) as u32;
-                    sorts::cocktail_shaker_sort(data_arc_cln.clone(), &sleep_time);
-                });
-            }
-            SortInstruction::ShellSort => {
-                start_sort_thread!(self, data_arc_cln, {
-                    let sleep_time = sleep_times_cln.shell/((data_len as f32).powf(3.0/2.0).floor() as u32);
-                    sorts::shell_sort(data_arc_cln.clone(), &sleep_time);
-                });
-            }
-            SortInstruction::RadixSort(base) => {
-                start_sort_thread!(self, data_arc_cln, {
-                    let sleep_time = sleep_times_cln.radix/data_len as u32;
-                    sorts::radix_lsd(data_arc_cln.clone(), &sleep_time, base);
-                });
+    // Colour of an element as the displays that use red to green draw it, for frontends that do not draw with nannou.
+    pub fn element_colour(&self, index: usize) -> (f32, f32, f32) {
+        self.highlights.colour(index)
+            .unwrap_or_else(|| tools::hsv_to_rgb((self.arr[index] as f32 / self.max_val as f32) / 3.0, 1.0, 1.0))
+    }
+
+    // Sets the heap shape shown over the array, recording the change.
+    pub fn set_heap(&mut self, heap: Option<HeapShape>) {
+        let old = std::mem::replace(&mut self.heap, heap);
+        if old != heap {
+            self.record(Operation::Heap { old, new: heap });
+        }
+    }
+
+    // Shadows the slice's swap so that swaps are recorded.
+    #[inline]
+    pub fn swap(&mut self, a: usize, b: usize) {
+        self.arr.swap(a, b);
+        self.record(Operation::Swap(a, b));
+    }
+
+    #[inline]
+    pub fn set(&mut self, index: usize, value: usize) {
+        let old = std::mem::replace(&mut self.arr[index], value);
+        self.record(Operation::Write { index, old, new: value });
+    }
+
+    // Compares the elements at two indices.
+    #[inline]
+    pub fn compare(&mut self, a: usize, b: usize) -> Ordering {
+        self.record(Operation::Compare(a, b));
+        self.arr[a].cmp(&self.arr[b])
+    }
+
+    // Compares the element at an index with a value that the sort is holding onto.
+    #[inline]
+    pub fn compare_value(&mut self, index: usize, value: usize) -> Ordering {
+        self.record(Operation::CompareValue(index, value));
+        self.arr[index].cmp(&value)
+    }
+
+    // Checkpoint for sorting threads. Blocks while the sort is paused, letting one checkpoint through for each
+    // queued step. Returns true if the sort has been stopped.
+    pub fn wait_while_paused(data_arc: &RwLock<DataArrWrapper>) -> bool {
+        loop {
+            {
+                let read = data_arc.read().unwrap();
+                if read.sorted {
+                    return true;
+                } else if !read.paused {
+                    return false;
+                }
             }
-            SortInstruction::MergeSort(merge_type) => {
-                match merge_type {
-                    MergeSortType::InPlace {
-                        multithreaded,
-                    } => {
-                        if multithreaded {
-                            start_sort_thread!(self, data_arc_cln, {
-                                let sleep_time = Arc::new(sleep_times_cln.merge/data_len as u32); //sleep_times_cln.merge/((data_len as f32).log10().floor() as u32 * data_len as u32);
-                                sorts::merge_sorting::merge_sort_in_place_multithreaded(data_arc_cln.clone(), sleep_time, 0, data_len - 1);
-                            });
-                        } else {
-                            start_sort_thread!(self, data_arc_cln, {
-                                let sleep_time = Arc::new(sleep_times_cln.merge/data_len as u32); //sleep_times_cln.merge/((data_len as f32).log10().floor() as u32 * data_len as u32);
-                                sorts::merge_sorting::merge_sort_in_place(data_arc_cln.clone(), sleep_time, 0, data_len - 1);
-                            });
-                        }
-                    }
+            {
+                let mut write = data_arc.write().unwrap();
+                if write.steps > 0 {
+                    write.steps -= 1;
+                    return write.sorted;
                 }
             }
+            thread::sleep(PAUSE_POLL_TIME);
+        }
+    }
+}
 
-            SortInstruction::Reset => {
-                self.reset();
-            }
-            SortInstruction::Reverse => {
-                self.data.write().unwrap().sorted = false;
-                self.data.write().unwrap().reverse();
-            }
-            SortInstruction::Stop => {
-                let mut write = self.data.write().unwrap();
-                write.sorted = true;
-            }
+// How far an element's value is from the value that belongs at its index, were the values spread evenly from 0 to
+// max_val. For arrays of every number up to the length this is how far the element is from where it is supposed to be.
+#[inline]
+fn disparity(value: usize, index: usize, len: usize, max_val: usize) -> f32 {
+    (value as f32 - index as f32 * max_val as f32 / len as f32).abs()
+}
+
+// Draws a strip alongside each highlighted range, with a row of strips for each highlight.
+// `rect_for_range` gives the (left, bottom, width, height) of the strip for a range's (start, length), where `offset`
+// is how far the row is from the edge of the window.
+fn draw_range_strips(
+    canvas: &mut impl Canvas,
+    ranges: &[DrawnRange],
+    transform: (f32, f32),
+    rect_for_range: impl Fn(usize, usize, f32) -> (f32, f32, f32, f32),
+) {
+    for range in ranges.iter() {
+        let (left, bottom, width, height) = rect_for_range(range.start, range.len, range.row as f32 * RANGE_STRIP_WIDTH);
+        canvas.rect(
+            [transform.0 + left + width/2.0, transform.1 + bottom + height/2.0],
+            (width, height),
+            range.colour.into(),
+        );
+    }
+}
+
+// Draws highlighted ranges as arcs around the inside edge of a circle, with a ring of arcs for each highlight.
+fn draw_range_arcs(canvas: &mut impl Canvas, ranges: &[DrawnRange], centre: Point, radius: f32, angle_interval: f32) {
+    for range in ranges.iter() {
+        let outer = radius - range.row as f32 * RANGE_STRIP_WIDTH;
+        let inner = outer - RANGE_STRIP_WIDTH;
+
+        for i in range.start..range.start + range.len {
+            let angle = i as f32 * angle_interval;
+            let connecting_angle = angle + angle_interval;
+            canvas.quad(
+                [
+                    point_around(centre, outer, angle),
+                    point_around(centre, outer, connecting_angle),
+                    point_around(centre, inner, connecting_angle),
+                    point_around(centre, inner, angle),
+                ],
+                range.colour.into(),
+            );
         }
     }
+}
 
-    #[inline]
-    pub fn display(
+// Point at an angle around the centre of a circular display.
+#[inline]
+fn point_around(centre: Point, radius: f32, angle: f32) -> Point {
+    let [x, y] = tools::get_point_on_radius(radius, angle);
+    [centre[0] + x, centre[1] + y]
+}
+
+// Colour of an element in the displays that go from red to green, or its highlight's colour.
+#[inline]
+fn red_grn_colour(data: &DataArrWrapper, index: usize) -> Colour {
+    data.element_colour(index).into()
+}
+
+impl DataArrWrapper {
+    // Draws the array on any canvas. `index` and `max_index` place the array's row in the Pixels display, and every
+    // other display is drawn in the window's dimensions with `transform` being the bottom left corner (circular
+    // displays are centred in that area).
+    pub fn draw(
         &self,
-        draw: &Draw,
+        canvas: &mut impl Canvas,
         index: usize,
         max_index: usize,
         array_len: usize,
@@ -219,51 +300,49 @@ impl SortArray {
         transform: (f32, f32),
         doughnut_ratio: f32,
     ) {
-        let data_read = self.data.read().unwrap();
+        let ranges = self.highlights.ranges();
 
         match mode {
             DisplayMode::Bars => {
                 let scale = (
                     window_dims.0 / array_len as f32,
-                    window_dims.1 / data_read.max_val as f32,
+                    window_dims.1 / self.max_val as f32,
                 );
 
-                for (i, d) in data_read.iter().enumerate() {
-                    let x = (i as f32 * scale.0) + scale.0 / 2.0;
+                for (i, d) in self.iter().enumerate() {
+                    let x = transform.0 + (i as f32 * scale.0) + scale.0 / 2.0;
                     let y = (*d as f32 + 1.0) * scale.1;
 
-                    let drawing = draw
-                        .line()
-                        .x_y(transform.0, transform.1)
-                        .start(Point2::new(x, 0.0))
-                        .end(Point2::new(x, y))
-                        .weight(scale.0);
-
-                    colour_element_red_grn_clrs!(data_read, i, drawing, data_read.max_val, d);
+                    canvas.line([x, transform.1], [x, transform.1 + y], scale.0, red_grn_colour(self, i));
                 }
+
+                draw_range_strips(canvas, &ranges, transform, |start, len, offset| {
+                    (start as f32 * scale.0, offset, len as f32 * scale.0, RANGE_STRIP_WIDTH)
+                });
             }
             DisplayMode::DisparityLine => {
                 let scale = (
                     window_dims.0 / array_len as f32,
-                    window_dims.1 / (2 * data_read.max_val) as f32,
+                    window_dims.1 / (2 * self.max_val) as f32,
                 );
 
                 let half_height = window_dims.1 as f32/2.0;
 
-                for (i, d) in data_read.iter().enumerate() {
-                    // ((*d as f32 - i as f32).abs() + 1.0) * scale.1
-                    let x = i as f32 * scale.0 - scale.0/2.0;
-                    let diff = ((*d as f32 - i as f32).abs() + 1.0) * scale.1;
-                    
-                    let drawing = draw
-                        .line()
-                        .x_y(transform.0, transform.1)
-                        .start(Point2::new(x, half_height - diff))
-                        .end(Point2::new(x, half_height + diff))
-                        .weight(scale.0);
-
-                    colour_element_red_grn_clrs!(data_read, i, drawing, data_read.max_val, d);
+                for (i, d) in self.iter().enumerate() {
+                    let x = transform.0 + i as f32 * scale.0 - scale.0/2.0;
+                    let diff = (disparity(*d, i, array_len, self.max_val) + 1.0) * scale.1;
+
+                    canvas.line(
+                        [x, transform.1 + half_height - diff],
+                        [x, transform.1 + half_height + diff],
+                        scale.0,
+                        red_grn_colour(self, i),
+                    );
                 }
+
+                draw_range_strips(canvas, &ranges, transform, |start, len, offset| {
+                    (start as f32 * scale.0, offset, len as f32 * scale.0, RANGE_STRIP_WIDTH)
+                });
             }
             DisplayMode::DisparityLoop | DisplayMode::Spiral => {   // For circles with small elements
                 const RING_THICKNESS: f32 = 6.0;
@@ -272,171 +351,491 @@ impl SortArray {
                 const SQUARE_MIN_SIZE: f32 = SQUARE_STROKE_WEIGHT * 2.0;
 
                 let max_radius = window_dims.0.min(window_dims.1) / 2.0;
+                let centre = [transform.0 + window_dims.0 / 2.0, transform.1 + window_dims.1 / 2.0];
 
                 let angle_interval = TWO_PI / array_len as f32;
                 let square_dims = ((max_radius * angle_interval).max(RING_THICKNESS) * SQUARE_SIZE_MUL).max(SQUARE_MIN_SIZE);  // Longest length of largest posible segment
 
                 let mut angle = 0.0;
 
-                for (i, d) in data_read.iter().enumerate() {
+                for (i, d) in self.iter().enumerate() {
                     let connecting_angle = angle + angle_interval;
 
                     let outer_radius = match mode {
                         DisplayMode::DisparityLoop => {
-                            let ratio = 1.0 - ((*d as f32 - i as f32).abs() + 1.0)/data_read.max_val as f32;    // Ratio of disparity
+                            let ratio = 1.0 - (disparity(*d, i, array_len, self.max_val) + 1.0)/self.max_val as f32;    // Ratio of disparity
                             max_radius * ratio
                         },
                         DisplayMode::Spiral => {
-                            (1.0 - *d as f32/data_read.max_val as f32) * max_radius
+                            (1.0 - *d as f32/self.max_val as f32) * max_radius
                         },
                         _ => panic!("This is actually (almost) impossible."),
                     };
 
                     let points = [
-                        tools::get_point_on_radius(outer_radius, angle),
-                        tools::get_point_on_radius(outer_radius, connecting_angle),
-                        tools::get_point_on_radius(outer_radius - RING_THICKNESS, connecting_angle),
-                        tools::get_point_on_radius(outer_radius - RING_THICKNESS, angle),
+                        point_around(centre, outer_radius, angle),
+                        point_around(centre, outer_radius, connecting_angle),
+                        point_around(centre, outer_radius - RING_THICKNESS, connecting_angle),
+                        point_around(centre, outer_radius - RING_THICKNESS, angle),
                     ];
 
-                    let midpoint = Point2::new( // Midpoint of segment
+                    let midpoint = [ // Midpoint of segment
                         points[0][0] + (points[1][0] - points[0][0])/2.0,
                         points[0][1] + (points[1][1] - points[0][1])/2.0,
-                    );
+                    ];
 
-                    draw.quad()
-                        .points(points[0], points[1], points[2], points[3])
-                        .hsv(*d as f32 / data_read.max_val as f32, 1.0, 1.0);
-
-                    if Some(i) == data_read.active {    // If is the active element, draw a box around it
-                        draw.rect()
-                            .rgb(0.0, 0.0, 0.0)
-                            .stroke(LinSrgba::new(0.9, 0.9, 0.9, 1.0))
-                            .stroke_weight(SQUARE_STROKE_WEIGHT)
-                            .xy(midpoint)
-                            .w_h(square_dims, square_dims);
+                    canvas.quad(points, Colour::hsv(*d as f32 / self.max_val as f32, 1.0, 1.0));
+
+                    if let Some(colour) = self.highlights.colour(i) {    // If the element has a marker, draw a box around it
+                        canvas.rect(midpoint, (square_dims, square_dims), Colour::BLACK);
+                        canvas.rect_outline(midpoint, (square_dims, square_dims), SQUARE_STROKE_WEIGHT, colour.into());
                     }
 
                     angle = connecting_angle;
                 }
+
+                draw_range_arcs(canvas, &ranges, centre, max_radius, angle_interval);
             }
             DisplayMode::Pyramid => {
                 let scale = (
-                    window_dims.0 / (2 * data_read.max_val) as f32,
+                    window_dims.0 / (2 * self.max_val) as f32,
                     window_dims.1 / array_len as f32,
                 );
 
                 let half_width = window_dims.0 as f32/2.0;
 
-                for (i, d) in data_read.iter().enumerate() {
-                    let y = (array_len - i) as f32 * scale.1 - scale.1/2.0;
+                for (i, d) in self.iter().enumerate() {
+                    let y = transform.1 + (array_len - i) as f32 * scale.1 - scale.1/2.0;
                     let diff = (*d as f32 + 1.0) * scale.0;
-                    
-                    let drawing = draw
-                        .line()
-                        .x_y(transform.0, transform.1)
-                        .start(Point2::new(half_width - diff, y))
-                        .end(Point2::new(half_width + diff, y))
-                        .weight(scale.1);
-
-                    colour_element_red_grn_clrs!(data_read, i, drawing, data_read.max_val, d);
+
+                    canvas.line(
+                        [transform.0 + half_width - diff, y],
+                        [transform.0 + half_width + diff, y],
+                        scale.1,
+                        red_grn_colour(self, i),
+                    );
                 }
+
+                draw_range_strips(canvas, &ranges, transform, |start, len, offset| {
+                    (offset, (array_len - start - len) as f32 * scale.1, RANGE_STRIP_WIDTH, len as f32 * scale.1)
+                });
             }
             DisplayMode::Circle | DisplayMode::Doughnut => {
                 let radius = window_dims.0.min(window_dims.1) / 2.0;
+                let centre = [transform.0 + window_dims.0 / 2.0, transform.1 + window_dims.1 / 2.0];
 
                 let angle_interval = TWO_PI / array_len as f32;
                 let mut angle = 0.0;
 
-                for d in data_read.iter() {
+                for (i, d) in self.iter().enumerate() {
                     let connecting_angle = angle + angle_interval;
 
-                    draw.tri()
-                        .points(
-                            [0.0, 0.0],
-                            tools::get_point_on_radius(radius, angle),
-                            tools::get_point_on_radius(radius, connecting_angle),
-                        )
-                        .hsv(*d as f32 / data_read.max_val as f32, 1.0, 1.0);
+                    let colour = match self.highlights.colour(i) {
+                        Some(colour) => colour.into(),
+                        None => Colour::hsv(*d as f32 / self.max_val as f32, 1.0, 1.0),
+                    };
+                    canvas.tri(
+                        [
+                            centre,
+                            point_around(centre, radius, angle),
+                            point_around(centre, radius, connecting_angle),
+                        ],
+                        colour,
+                    );
 
                     angle = connecting_angle;
                 }
 
+                draw_range_arcs(canvas, &ranges, centre, radius, angle_interval);
+
                 if mode == DisplayMode::Doughnut {  // Draw hole in doughnut
-                    draw.ellipse()
-                        .radius(radius * doughnut_ratio)
-                        .resolution(data_read.max_val)  // Lines up with each colour section
-                        .color(BLACK);
+                    // Resolution lines up with each colour section
+                    canvas.ellipse(centre, radius * doughnut_ratio, Some(array_len), Colour::BLACK);
                 }
             }
             DisplayMode::Dots => {
                 let scale = (
                     window_dims.0 / array_len as f32,
-                    window_dims.1 / data_read.max_val as f32,
+                    window_dims.1 / self.max_val as f32,
                 );
 
-                for (i, d) in data_read.iter().enumerate() {
-                    let drawing = draw
-                        .ellipse()
-                        .x_y(
+                for (i, d) in self.iter().enumerate() {
+                    canvas.ellipse(
+                        [
                             transform.0 + ((i as f32 * scale.0) + scale.0 / 2.0),
                             transform.1 + ((*d as f32 + 0.5) * scale.1),
-                        )
-                        .radius(scale.0 / 2.0);
-
-                    colour_element_red_grn_clrs!(data_read, i, drawing, data_read.max_val, d);
+                        ],
+                        scale.0 / 2.0,
+                        None,
+                        red_grn_colour(self, i),
+                    );
                 }
+
+                draw_range_strips(canvas, &ranges, transform, |start, len, offset| {
+                    (start as f32 * scale.0, offset, len as f32 * scale.0, RANGE_STRIP_WIDTH)
+                });
             }
             DisplayMode::Pixels => {
                 let scale = (
-                    window_dims.0 / data_read.max_val as f32,
+                    window_dims.0 / array_len as f32,
                     window_dims.1 / max_index as f32,
                 );
                 let y = (index as f32 + 0.5) * scale.1;
 
-                for (i, d) in data_read.iter().enumerate() {
-                    draw.rect()
-                        .x_y(transform.0 + (i as f32 + 0.5) * scale.0, transform.1 + y)
-                        .w_h(scale.0, scale.1)
-                        .hsv((1.0 - (*d as f32 / data_read.max_val as f32)) / 3.0, 1.0, 1.0);
+                for (i, d) in self.iter().enumerate() {
+                    let colour = match self.highlights.colour(i) {
+                        Some(colour) => colour.into(),
+                        None => Colour::hsv((1.0 - (*d as f32 / self.max_val as f32)) / 3.0, 1.0, 1.0),
+                    };
+                    canvas.rect([transform.0 + (i as f32 + 0.5) * scale.0, transform.1 + y], (scale.0, scale.1), colour);
+                }
+
+                // Rows are too thin for strips, so ranges are shaded over the elements instead.
+                for range in ranges.iter() {
+                    let (r, g, b) = range.colour;
+                    canvas.rect(
+                        [transform.0 + (range.start as f32 + range.len as f32 / 2.0) * scale.0, transform.1 + y],
+                        (range.len as f32 * scale.0, scale.1),
+                        Colour::rgba(r, g, b, 0.35),
+                    );
                 }
             }
         }
     }
+}
+
+pub struct SortArray {
+    pub data: Arc<RwLock<DataArrWrapper>>,
+    counters: Arc<SortCounters>,
+    current_sort: Option<usize>,    // Sort that the counters are for
+    sorts: Arc<SortRegistry>,
+    distribution: Distribution,     // Used to generate the array when it is made or reset
+    loaded: Option<LoadedArray>,    // Used instead of the distribution when the array was loaded from a file
+    shuffle_type: ShuffleType,
+    shuffle_sleep: Duration,
+    sort_thread: Option<thread::JoinHandle<()>>,
+    pool: Option<Arc<SortPool>>,    // Started the first time the array sorts
+}
+
+impl SortArray {
+    pub fn new(
+        num_of_lines: usize,
+        sorts: Arc<SortRegistry>,
+        distribution: Distribution,
+        shuffle_type: ShuffleType,
+        shuffle_sleep: Duration,
+        trace_limit: usize,
+    ) -> SortArray {
+        let values = distribution.generate(num_of_lines, &sorts.rng);
+        let max_val = distribution::max_value(&values);     // Then when drawing you can scale it however you want.
+        let mut data = DataArrWrapper::new(values, max_val);
+        data.trace_limit = trace_limit;
+
+        SortArray {
+            data: Arc::new(RwLock::new(data)),
+            counters: Arc::new(SortCounters::default()),
+            current_sort: None,
+            sorts,
+            distribution,
+            loaded: None,
+            shuffle_type,
+            shuffle_sleep,
+            sort_thread: None,
+            pool: None,
+        }
+    }
+
+    // The array's own pool for multithreaded sorts, so that pausing it does not hold up the sorts of other arrays.
+    fn pool(&mut self) -> Arc<SortPool> {
+        let sorts = &self.sorts;
+        Arc::clone(self.pool.get_or_insert_with(|| Arc::new(sorts.pool.new_like())))
+    }
+
+    // Easier to handle in here rather than in main
+    pub fn instruction(&mut self, instruction: SortInstruction) {
+        let data_len = self.len();
+
+        match instruction {
+            SortInstruction::Shuffle => {
+                // Shuffling is not counted, so it gets its own counters.
+                self.clear_counters();
+                let shuffle_sleep = self.shuffle_sleep/Complexity::NSquared.divisor(data_len);
+                let shuffle_type = self.shuffle_type;
+                start_sort_thread!(self, Arc::new(SortCounters::default()), shuffle_sleep, |ctx| shuffles::shuffle(&ctx, shuffle_type));
+            }
+            SortInstruction::Sort(id) => {
+                let sorts = Arc::clone(&self.sorts);
+                self.current_sort = Some(id);
+                start_sort_thread!(self, Arc::clone(&self.counters), sorts[id].sleep_time_for(data_len), |ctx| sorts[id].algorithm.run(&ctx));
+            }
+
+            SortInstruction::Reset => {
+                self.reset();
+            }
+            SortInstruction::Reverse => {
+                let mut write = self.data.write().unwrap();
+                write.sorted = false;
+                write.trace = None; // Trace no longer matches the array
+                write.reverse();
+            }
+            SortInstruction::Stop => {
+                let mut write = self.data.write().unwrap();
+                write.sorted = true;
+            }
+            SortInstruction::Pause => {
+                self.data.write().unwrap().paused = true;
+            }
+            SortInstruction::Resume => {
+                let mut write = self.data.write().unwrap();
+                write.paused = false;
+                write.steps = 0;
+            }
+            SortInstruction::Step => {
+                let mut write = self.data.write().unwrap();
+                if write.paused {
+                    write.steps += 1;
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub fn display(
+        &self,
+        draw: &Draw,
+        index: usize,
+        max_index: usize,
+        array_len: usize,
+        mode: DisplayMode,
+        window_dims: (f32, f32),
+        transform: (f32, f32),
+        doughnut_ratio: f32,
+    ) {
+        self.data.read().unwrap().draw(
+            &mut DrawCanvas(draw),
+            index,
+            max_index,
+            array_len,
+            mode,
+            window_dims,
+            transform,
+            doughnut_ratio,
+        );
+    }
 
     #[inline]
     pub fn len(&self) -> usize {
         self.data.read().unwrap().len()
     }
 
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.data.read().unwrap().paused
+    }
+
+    // Whether a sort has run and left the array in order.
+    pub fn is_finished(&self) -> bool {
+        let data_read = self.data.read().unwrap();
+        data_read.sorted && self.current_sort.is_some() && data_read.windows(2).all(|pair| pair[0] <= pair[1])
+    }
+
+    #[inline]
+    pub fn counters(&self) -> CounterSnapshot {
+        self.counters.snapshot()
+    }
+
+    // Name of the sort that the counters are for, with its options.
+    pub fn current_sort_name(&self) -> Option<String> {
+        self.current_sort.map(|id| self.sorts[id].algorithm.full_name())
+    }
+
+    #[inline]
+    fn clear_counters(&mut self) {
+        self.counters.reset();
+        self.current_sort = None;
+    }
+
+    // Name of the current sort, the array length and the counters, one per line.
+    pub fn stats_text(&self) -> String {
+        let counters = self.counters();
+        format!(
+            "{}\nLength: {} ({})\nComparisons: {}\nSwaps: {}\nReads: {}\nWrites: {}\nTime: {:.2}s\nBusy workers: {}/{}\nSeed: {}",
+            self.current_sort_name().unwrap_or_else(|| String::from("No sort")),
+            self.len(),
+            self.loaded.as_ref().map_or(self.distribution.name(), |loaded| &loaded.name),
+            counters.comparisons,
+            counters.swaps,
+            counters.reads,
+            counters.writes,
+            counters.elapsed.as_secs_f32(),
+            self.pool.as_ref().map_or(0, |pool| pool.busy_workers()),
+            self.sorts.pool.workers(),
+            self.sorts.rng.seed(),
+        )
+    }
+
+    // Draws the stats in the top left of the window.
+    pub fn display_stats(&self, draw: &Draw, window_dims: (f32, f32)) {
+        const LINE_HEIGHT: f32 = 18.0;
+        const MARGIN: f32 = 10.0;
+
+        let text = self.stats_text();
+        let height = LINE_HEIGHT * text.lines().count() as f32;
+
+        draw.text(&text)
+            .x_y(0.0, window_dims.1 / 2.0 - height / 2.0 - MARGIN)
+            .w_h(window_dims.0 - 2.0 * MARGIN, height)
+            .left_justify()
+            .font_size(14)
+            .color(WHITE);
+    }
+
+    // Draws the heap that a heap sort is building/using as a tree above the array (below the stats), with the nodes
+    // being compared highlighted like in the Bars display. Levels too crowded to see are left out.
+    pub fn display_heap_tree(&self, draw: &Draw, window_dims: (f32, f32), transform: (f32, f32)) {
+        const TOP_MARGIN: f32 = 150.0;          // Leaves room for the stats
+        const BOTTOM_RATIO: f32 = 0.45;         // Bottom of the tree as a ratio of the window height
+        const MIN_NODE_SPACING: f32 = 6.0;
+        const MAX_NODE_RADIUS: f32 = 8.0;
+
+        let data_read = self.data.read().unwrap();
+        let heap = match data_read.heap {
+            Some(heap) if heap.len > 0 => heap,
+            _ => return,
+        };
+
+        // Number of levels that are in the heap and not too crowded
+        let mut levels = 0;
+        let mut level_start = 0;
+        let mut level_size = 1;
+        while level_start < heap.len && window_dims.0 / level_size as f32 >= MIN_NODE_SPACING {
+            levels += 1;
+            level_start += level_size;
+            level_size *= heap.arity;
+        }
+
+        let top = window_dims.1 - TOP_MARGIN;
+        let bottom = window_dims.1 * BOTTOM_RATIO;
+        if levels == 0 || top <= bottom {
+            return;
+        }
+        let level_height = (top - bottom) / levels as f32;
+
+        draw.rect()
+            .x_y(transform.0 + window_dims.0/2.0, transform.1 + (top + bottom)/2.0)
+            .w_h(window_dims.0, top - bottom)
+            .rgba(0.0, 0.0, 0.0, 0.75);
+
+        // Position of a node, given its level and index within the level
+        let position = |level: usize, index: usize| {
+            let spacing = window_dims.0 / heap.arity.pow(level as u32) as f32;
+            Point2::new(
+                transform.0 + (index as f32 + 0.5) * spacing,
+                transform.1 + top - (level as f32 + 0.5) * level_height,
+            )
+        };
+
+        // (level, index in level) of every node drawn. Element i is the node at level_start + index.
+        let mut nodes = Vec::new();
+        let mut level_start = 0;
+        let mut level_size = 1;
+        for level in 0..levels {
+            for index in 0..level_size.min(heap.len - level_start) {
+                nodes.push((level, index, level_start + index));
+            }
+            level_start += level_size;
+            level_size *= heap.arity;
+        }
+
+        // Edges first so that they are under the nodes
+        for &(level, index, _) in nodes.iter().filter(|(level, ..)| *level > 0) {
+            draw.line()
+                .start(position(level - 1, index / heap.arity))
+                .end(position(level, index))
+                .weight(1.0)
+                .color(GREY);
+        }
+
+        for &(level, index, i) in nodes.iter() {
+            let spacing = window_dims.0 / heap.arity.pow(level as u32) as f32;
+            let drawing = draw.ellipse()
+                .xy(position(level, index))
+                .radius((spacing * 0.4).min(level_height * 0.3).min(MAX_NODE_RADIUS));
+
+            colour_element_red_grn_clrs!(data_read, i, drawing, data_read.max_val, &data_read[i]);
+        }
+    }
+
     #[inline]
     fn reset_arr_info(data_arc: Arc<RwLock<DataArrWrapper>>) {
         let mut write = data_arc.write().unwrap();
 
-        write.active = None;
-        write.active_2 = None;
-        write.pivot = None;
+        // Recorded, so that a trace ends in the same state as the array
+        write.clear_highlights();
+        write.set_heap(None);
         write.sorted = true;
+        write.paused = false;
+        write.steps = 0;
     }
 
     pub fn reset(&mut self) {
+        let values = match &self.loaded {
+            Some(loaded) => loaded.values.clone(),
+            None => self.distribution.generate(self.len(), &self.sorts.rng),
+        };
+        self.set_values(values);
+    }
+
+    // Stops the sort and waits for its thread to end, so that it can't touch the array after this returns.
+    fn stop_and_join(&mut self) {
+        self.data.write().unwrap().sorted = true;
+        if let Some(sort_thread) = self.sort_thread.take() {
+            let _ = sort_thread.join();     // A sort that panicked has nothing left to clean up
+        }
+    }
+
+    // Replaces the array's values, stopping any sort and clearing its counters.
+    pub fn set_values(&mut self, values: Vec<usize>) {
+        self.stop_and_join();
+        self.clear_counters();
         Self::reset_arr_info(self.data.clone());
         let mut write = self.data.write().unwrap();
-        write.arr = (0..write.len()).collect();
+        write.max_val = distribution::max_value(&values);
+        write.arr = values;
+        write.trace = None;
     }
 
-    fn shuffle(data: Arc<RwLock<DataArrWrapper>>, sleep_time: &Duration, passes: u16) {
-        let len = data.read().unwrap().len();
+    // Changes the distribution and regenerates the array with it.
+    pub fn set_distribution(&mut self, distribution: Distribution) {
+        self.distribution = distribution;
+        self.loaded = None;
+        self.reset();
+    }
 
-        for _ in 0..passes {
-            for i in 0..len {
-                {
-                    let mut data_write = data.write().unwrap();
-                    data_write.swap(i, nannou::rand::random_range(0usize, len));
-                    data_write.set_active(i);
-                }
-                thread::sleep(*sleep_time);
-            }
+    // Replaces the array with values from a file. Resetting goes back to these values until the distribution changes.
+    pub fn load(&mut self, name: String, values: Vec<usize>) {
+        self.loaded = Some(LoadedArray { name, values });
+        self.reset();
+    }
+
+    // Copy of the current values, for saving.
+    #[inline]
+    pub fn values(&self) -> Vec<usize> {
+        self.data.read().unwrap().arr.clone()
+    }
+
+    // Takes the recorded trace out of the array to be replayed. Only possible when no sort is running.
+    pub fn take_trace(&mut self) -> Option<Vec<Operation>> {
+        let mut write = self.data.write().unwrap();
+        if write.sorted {
+            write.trace.take()
+        } else {
+            None
         }
     }
-}
\ No newline at end of file
+
+    #[inline]
+    pub fn return_trace(&mut self, trace: Vec<Operation>) {
+        self.data.write().unwrap().trace = Some(trace);
+    }
+}
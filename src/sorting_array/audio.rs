@@ -1,34 +1,224 @@
 use std::str::FromStr;
+use std::f64::consts::PI;
 use std::io;
+use std::path::Path;
+use std::time::Duration;
 
+use yaml_rust::Yaml;
+
+use super::{DataArrWrapper, highlight::{ACTIVE, ACTIVE_2}};
+
+pub const SOUND_DURATION: Duration = Duration::from_millis(100);   // How long each sound is held for, before its release
+pub const SAMPLE_RATE: u32 = 44_100;    // Of rendered WAV files
+const MAX_VOICES: usize = 16;           // The oldest voice is dropped to make room for a new one past this
+
+// Synthesiser. Every sound gets its own voice, shaped by the envelope, so sounds can overlap and play as chords. Used
+// by the live output stream, and offline when rendering to a file, so both sound the same.
 #[derive(Default, Debug)]
 pub struct Audio {
-    pub phase: f64,
-    pub hz: f64,
     pub min_hz: f64,
     pub max_hz: f64,
-    pub volume: f32,
-    pub waveform: Waveform
+    pub volume: f32,    // Of each voice
+    pub waveform: Waveform,
+    pub envelope: Envelope,
+    voices: Vec<Voice>,
+}
+
+#[derive(Debug)]
+struct Voice {
+    phase: f64,     // From 0 to 1 through each cycle
+    hz: f64,
+    age: f64,       // Seconds since it started
 }
 
 impl Audio {
-    pub fn new(min_hz: f64, max_hz: f64, waveform: Waveform) -> Self {
+    pub fn new(min_hz: f64, max_hz: f64, waveform: Waveform, envelope: Envelope) -> Self {
         Self {
             min_hz,
             max_hz,
             volume: 0.25,
             waveform,
+            envelope,
             ..Default::default()
         }
     }
+
+    // Plays a sound, with the pitch interpolated between min_hz and max_hz by `ratio` (0 to 1).
+    pub fn play(&mut self, ratio: f64) {
+        if self.voices.len() >= MAX_VOICES {
+            self.voices.remove(0);
+        }
+        self.voices.push(Voice {
+            phase: 0.0,
+            hz: pitch(self.min_hz, self.max_hz, ratio),
+            age: 0.0,
+        });
+    }
+
+    // Plays every sound at once.
+    pub fn play_chord(&mut self, ratios: &[f64]) {
+        for &ratio in ratios {
+            self.play(ratio);
+        }
+    }
+
+    // Number of voices still sounding.
+    #[inline]
+    pub fn voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    // Next sample of every voice mixed together, or silence when none are playing.
+    pub fn next_sample(&mut self, sample_rate: f64) -> f32 {
+        let held = SOUND_DURATION.as_secs_f64();
+        let mut amp = 0.0;
+
+        for voice in self.voices.iter_mut() {
+            amp += self.waveform.sample(voice.phase) * self.envelope.level(voice.age, held);
+            voice.phase = (voice.phase + voice.hz / sample_rate) % 1.0;
+            voice.age += 1.0 / sample_rate;
+        }
+
+        let envelope = &self.envelope;
+        self.voices.retain(|voice| !envelope.finished(voice.age, held));
+        (amp * self.volume).clamp(-1.0, 1.0)
+    }
+
+    // Renders mono samples onto the end of `samples` until it is `until` seconds long.
+    pub fn render_until(&mut self, samples: &mut Vec<f32>, until: f64, sample_rate: u32) {
+        let end = (until * sample_rate as f64).round() as usize;
+        while samples.len() < end {
+            samples.push(self.next_sample(sample_rate as f64));
+        }
+    }
 }
 
-#[derive(Debug)]
+// Frequency for a sound, interpolated between the lowest and highest by `ratio` (0 to 1).
+#[inline]
+pub fn pitch(min_hz: f64, max_hz: f64, ratio: f64) -> f64 {
+    min_hz + (max_hz - min_hz) * ratio
+}
+
+// Attack, decay, sustain, release envelope. Times are in seconds, and sustain is the level held at after the decay.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Envelope {
+    pub attack: f64,
+    pub decay: f64,
+    pub sustain: f32,
+    pub release: f64,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Self {
+            attack: 0.005,
+            decay: 0.02,
+            sustain: 0.7,
+            release: 0.05,
+        }
+    }
+}
+
+impl Envelope {
+    // Level from 0 to 1, `age` seconds after a sound started that was held for `held` seconds.
+    pub fn level(&self, age: f64, held: f64) -> f32 {
+        if age >= held {
+            let release = if self.release > 0.0 { 1.0 - (age - held) / self.release } else { 0.0 };
+            return self.held_level(held) * release.max(0.0) as f32;
+        }
+        self.held_level(age)
+    }
+
+    #[inline]
+    pub fn finished(&self, age: f64, held: f64) -> bool {
+        age >= held + self.release
+    }
+
+    // Level while the sound is still held.
+    fn held_level(&self, age: f64) -> f32 {
+        if age < self.attack {
+            (age / self.attack) as f32
+        } else if age < self.attack + self.decay {
+            1.0 - (1.0 - self.sustain) * ((age - self.attack) / self.decay) as f32
+        } else {
+            self.sustain
+        }
+    }
+}
+
+impl From<&Yaml> for Envelope {
+    fn from(conf: &Yaml) -> Self {
+        let millis = |key: &str| {
+            conf[key].as_f64()
+                .unwrap_or_else(|| panic!("Could not parse {} field in config as a float.", key)) / 1000.0
+        };
+
+        Self {
+            attack: millis("attack"),
+            decay: millis("decay"),
+            sustain: conf["sustain"].as_f64()
+                .expect("Could not parse sustain field in config as a float.") as f32,
+            release: millis("release"),
+        }
+    }
+}
+
+// Pitches (from 0 to 1) of the sounds that the last changes to the active highlights should make, if any are waiting
+// to be played: one for the active element, and a second for the element it is being compared with. Taking them
+// means that they are not played again.
+pub fn take_sound(data: &mut DataArrWrapper) -> Option<Vec<f64>> {
+    if !data.should_play_sound {
+        return None;
+    }
+    data.should_play_sound = false;
+
+    let ratios: Vec<f64> = [ACTIVE, ACTIVE_2].iter()
+        .filter_map(|&highlight| data.highlights.first_marker(highlight))
+        .map(|index| data[index] as f64 / data.max_val as f64)
+        .collect();
+
+    if ratios.is_empty() { None } else { Some(ratios) }
+}
+
+// Saves mono samples as a 16 bit WAV file.
+pub fn write_wav(path: &Path, samples: &[f32], sample_rate: u32) -> io::Result<()> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let to_io = |err: hound::Error| io::Error::new(io::ErrorKind::Other, err);
+
+    let mut writer = hound::WavWriter::create(path, spec).map_err(to_io)?;
+    for &sample in samples {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).map_err(to_io)?;
+    }
+    writer.finalize().map_err(to_io)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Waveform {
     Sine,
     Haversine,
     Square,
     Triangle,
+    Sawtooth,
+}
+
+impl Waveform {
+    // Amplitude from -1 to 1 at a point in the cycle (`phase`, from 0 to 1). Every waveform is in phase with the sine
+    // wave, so is at its highest in the first half of the cycle.
+    pub fn sample(self, phase: f64) -> f32 {
+        let amp = match self {
+            Waveform::Sine => (2.0 * PI * phase).sin(),
+            Waveform::Haversine => (2.0 * PI * phase).sin().max(0.0),    // > 0.0
+            Waveform::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            Waveform::Triangle => 1.0 - 4.0 * ((phase + 0.25).fract() - 0.5).abs(),
+            Waveform::Sawtooth => 2.0 * (phase + 0.5).fract() - 1.0,
+        };
+        amp as f32
+    }
 }
 
 impl Default for Waveform {
@@ -46,12 +236,79 @@ impl FromStr for Waveform {
             "hsin" => Ok(Waveform::Haversine),
             "square" => Ok(Waveform::Square),
             "triangle" => Ok(Waveform::Triangle),
+            "sawtooth" => Ok(Waveform::Sawtooth),
             x => Err(
                 io::Error::new(
                     io::ErrorKind::Other,
-                    format!("Invalid waveform format in config file: {}. Options are: sin, hsin, square, triangle", x)
+                    format!("Invalid waveform format in config file: {}. Options are: sin, hsin, square, triangle, sawtooth", x)
                 )
             ),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sounds_take_their_pitch_from_the_active_elements() {
+        let mut data = DataArrWrapper::new(vec![0, 1, 2, 3], 4);
+        assert_eq!(take_sound(&mut data), None);
+
+        data.set_marker(ACTIVE, Some(2));
+        assert_eq!(take_sound(&mut data), Some(vec![0.5]));
+        assert_eq!(take_sound(&mut data), None);   // Only played once
+
+        // Comparing two elements plays both.
+        data.set_marker(ACTIVE_2, Some(1));
+        assert_eq!(take_sound(&mut data), Some(vec![0.5, 0.25]));
+
+        let mut audio_obj = Audio::new(100.0, 300.0, Waveform::Sine, Envelope::default());
+        audio_obj.play_chord(&[0.5, 0.25]);
+        assert_eq!(audio_obj.voices(), 2);
+    }
+
+    #[test]
+    fn waveforms_have_the_right_shape() {
+        const EPSILON: f32 = 1e-6;
+        let samples = |waveform: Waveform| -> Vec<f32> {
+            (0..8).map(|i| waveform.sample(i as f64 / 8.0)).collect()
+        };
+        let assert_samples = |waveform: Waveform, expected: [f32; 8]| {
+            for (i, (sample, expected)) in samples(waveform).iter().zip(expected.iter()).enumerate() {
+                assert!((sample - expected).abs() < EPSILON, "{:?} sample {} was {}, not {}", waveform, i, sample, expected);
+            }
+        };
+        let root_half = 0.5_f32.sqrt();
+
+        assert_samples(Waveform::Sine, [0.0, root_half, 1.0, root_half, 0.0, -root_half, -1.0, -root_half]);
+        assert_samples(Waveform::Haversine, [0.0, root_half, 1.0, root_half, 0.0, 0.0, 0.0, 0.0]);
+        assert_samples(Waveform::Square, [1.0, 1.0, 1.0, 1.0, -1.0, -1.0, -1.0, -1.0]);
+        assert_samples(Waveform::Triangle, [0.0, 0.5, 1.0, 0.5, 0.0, -0.5, -1.0, -0.5]);
+        assert_samples(Waveform::Sawtooth, [0.0, 0.25, 0.5, 0.75, -1.0, -0.75, -0.5, -0.25]);
+    }
+
+    #[test]
+    fn envelopes_rise_sustain_and_release_then_the_voice_stops() {
+        let envelope = Envelope { attack: 0.01, decay: 0.01, sustain: 0.5, release: 0.02 };
+        let held = SOUND_DURATION.as_secs_f64();
+
+        assert_eq!(envelope.level(0.0, held), 0.0);
+        assert!((envelope.level(0.005, held) - 0.5).abs() < 1e-6);     // Half way through the attack
+        assert!((envelope.level(0.015, held) - 0.75).abs() < 1e-6);    // Half way through the decay
+        assert_eq!(envelope.level(0.05, held), 0.5);
+        assert!((envelope.level(held + 0.01, held) - 0.25).abs() < 1e-6);  // Half way through the release
+        assert_eq!(envelope.level(held + 0.02, held), 0.0);
+
+        let mut audio_obj = Audio::new(100.0, 300.0, Waveform::Square, envelope);
+        audio_obj.play(0.0);
+        let mut samples = Vec::new();
+        audio_obj.render_until(&mut samples, held + 0.05, SAMPLE_RATE);
+
+        let release_end = ((held + envelope.release) * SAMPLE_RATE as f64) as usize;
+        assert!(samples[..release_end].iter().any(|&sample| sample.abs() > 0.1));
+        assert!(samples[release_end + 1..].iter().all(|&sample| sample == 0.0));
+        assert_eq!(audio_obj.voices(), 0);
+    }
+}
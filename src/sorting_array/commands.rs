@@ -4,46 +4,158 @@ use std::io::{self, ErrorKind};
 // Commands and options
 #[derive(Copy, Clone)]
 pub enum SortInstruction {
-    Shuffle(u16),
+    Shuffle,    // Uses the array's ShuffleType
     Reset,
     Reverse,
     Stop,
+    Pause,
+    Resume,
+    Step,   // Lets a paused sort advance by one compare, swap or write.
 
-    BubbleSort,
-    CocktailShakerSort,
-    InsertionSort,
-    SelectionSort,
+    Sort(usize),    // Index of the sort in the SortRegistry
+}
+
+// Which arrays an instruction is sent to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ArraySelector {
+    All,
+    Index(usize),   // Position of the array: its row in the Pixels display or its pane in a race
+}
+
+impl ArraySelector {
+    #[inline]
+    pub fn matches(&self, index: usize) -> bool {
+        match self {
+            ArraySelector::All => true,
+            ArraySelector::Index(selected) => *selected == index,
+        }
+    }
+}
+
+impl FromStr for ArraySelector {
+    type Err = io::Error;
+
+    // Format: <all|index>
+    fn from_str(s: &str) -> io::Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "all" => Ok(ArraySelector::All),
+            index => index.parse().map(ArraySelector::Index).map_err(|_| io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid array selector: {}. Options are: all, or the index of an array", s)
+            )),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Partitioning {
+    Lomuto,
+    Hoare,
+}
+
+impl Partitioning {
+    // Pivot used when none is given in the config.
+    pub fn default_pivot(&self) -> PivotSelection {
+        match self {
+            Partitioning::Lomuto => PivotSelection::Last,
+            Partitioning::Hoare => PivotSelection::Middle,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PivotSelection {
+    Last,
+    First,
+    Middle,
+    MedianOfThree,  // Median of the first, middle and last elements
+    Ninther,        // Median of three medians of three, spread across the partition
+    Random,
+}
+
+pub const ALL_PIVOT_SELECTIONS: [PivotSelection; 6] = [
+    PivotSelection::Last,
+    PivotSelection::First,
+    PivotSelection::Middle,
+    PivotSelection::MedianOfThree,
+    PivotSelection::Ninther,
+    PivotSelection::Random,
+];
 
-    ShellSort,
+impl PivotSelection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PivotSelection::Last => "last",
+            PivotSelection::First => "first",
+            PivotSelection::Middle => "middle",
+            PivotSelection::MedianOfThree => "median_of_three",
+            PivotSelection::Ninther => "ninther",
+            PivotSelection::Random => "random",
+        }
+    }
+}
 
-    QuickSort(QuickSortType),
-    MergeSort(MergeSortType),
-    
-    RadixSort(usize),
+impl FromStr for PivotSelection {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        ALL_PIVOT_SELECTIONS.iter()
+            .find(|pivot| pivot.name() == s.to_lowercase())
+            .copied()
+            .ok_or_else(|| io::Error::new(
+                ErrorKind::Other,
+                format!("Invalid quicksort pivot in config file: {}. Options are: last, first, middle, median_of_three, ninther, random", s)
+            ))
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
-pub enum QuickSortType {
-    Lomuto {
-        multithreaded: bool,
-		insertion_hybrid: bool,
-    },
+pub struct QuickSortType {
+    pub partitioning: Partitioning,
+    pub pivot: PivotSelection,
+    pub multithreaded: bool,
+    pub insertion_hybrid: bool,
 }
 
 impl FromStr for QuickSortType {
     type Err = io::Error;
 
+    // Format: <lomuto|hoare>[_insertion_hybrid][_multi][, <pivot>]
     fn from_str(s: &str) -> io::Result<Self> {
-        match s.to_lowercase().as_str() {
-            "lomuto" => Ok(QuickSortType::Lomuto { multithreaded: false, insertion_hybrid: false }),
-            "lomuto_multi" => Ok(QuickSortType::Lomuto { multithreaded: true, insertion_hybrid: false }),
-            "lomuto_insertion_hybrid" => Ok(QuickSortType::Lomuto { multithreaded: false, insertion_hybrid: true }),
-            "lomuto_insertion_hybrid_multi" => Ok(QuickSortType::Lomuto { multithreaded: true, insertion_hybrid: true }),
-            x => Err(io::Error::new(
-                ErrorKind::Other,
-                format!("Invalid quicksort_partitioning format in config file: {}. Options are: lomuto, lomuto_multi, lomuto_insertion_hybrid, lomuto_insertion_hybrid_multi", x)
-            )),
+        let invalid = || io::Error::new(
+            ErrorKind::Other,
+            format!("Invalid quicksort_partitioning format in config file: {}. Options are: lomuto, hoare, \
+followed by _insertion_hybrid and/or _multi, then optionally a comma and the pivot (e.g: hoare_multi, median_of_three)", s)
+        );
+
+        let lower = s.to_lowercase();
+        let mut parts = lower.split(',').map(str::trim);
+
+        let mut name = parts.next().unwrap_or_default();
+        let multithreaded = match name.strip_suffix("_multi") {
+            Some(stripped) => { name = stripped; true },
+            None => false,
+        };
+        let insertion_hybrid = match name.strip_suffix("_insertion_hybrid") {
+            Some(stripped) => { name = stripped; true },
+            None => false,
+        };
+
+        let partitioning = match name {
+            "lomuto" => Partitioning::Lomuto,
+            "hoare" => Partitioning::Hoare,
+            _ => return Err(invalid()),
+        };
+        let pivot = match parts.next() {
+            Some(pivot) => PivotSelection::from_str(pivot)?,
+            None => partitioning.default_pivot(),
+        };
+
+        if parts.next().is_some() {
+            return Err(invalid());
         }
+
+        Ok(QuickSortType { partitioning, pivot, multithreaded, insertion_hybrid })
     }
 }
 
@@ -71,6 +183,95 @@ impl FromStr for MergeSortType {
     }
 }
 
+#[derive(Copy, Clone, Debug)]
+pub enum HeapSortType {
+    Binary,
+    Ternary,
+}
+
+impl HeapSortType {
+    // Children per node
+    pub fn arity(&self) -> usize {
+        match self {
+            HeapSortType::Binary => 2,
+            HeapSortType::Ternary => 3,
+        }
+    }
+}
+
+impl FromStr for HeapSortType {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "binary" => Ok(HeapSortType::Binary),
+            "ternary" => Ok(HeapSortType::Ternary),
+            x => Err(
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("Invalid heap_sort_type format in config file: {}. Options are: binary, ternary", x)
+                )
+            ),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ShuffleType {
+    FisherYates,
+    Sattolo,    // Like Fisher-Yates, but always makes a single cycle, so no element stays where it was
+    Local {     // Each element is swapped with one at most `radius` after it
+        radius: usize,
+    },
+    RandomPairs {
+        pairs: usize,
+    },
+    ReverseRuns {   // Reverses randomly placed runs, each up to len/runs long
+        runs: usize,
+    },
+}
+
+impl ShuffleType {
+    // Used when no number is given in the config.
+    const DEFAULT_RADIUS: usize = 8;
+    const DEFAULT_PAIRS: usize = 20;
+    const DEFAULT_RUNS: usize = 8;
+}
+
+impl FromStr for ShuffleType {
+    type Err = io::Error;
+
+    // Format: <fisher_yates|sattolo|local|random_pairs|reverse_runs>[, <radius|pairs|runs>]
+    fn from_str(s: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(
+            ErrorKind::Other,
+            format!("Invalid shuffle_type format in config file: {}. Options are: fisher_yates, sattolo, local, random_pairs, \
+reverse_runs, with local, random_pairs and reverse_runs optionally followed by a comma and a number (e.g: local, 16)", s)
+        );
+
+        let lower = s.to_lowercase();
+        let mut parts = lower.split(',').map(str::trim);
+        let name = parts.next().unwrap_or_default();
+        let number = match parts.next() {
+            Some(number) => Some(number.parse::<usize>().map_err(|_| invalid())?),
+            None => None,
+        };
+
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        match (name, number) {
+            ("fisher_yates", None) => Ok(ShuffleType::FisherYates),
+            ("sattolo", None) => Ok(ShuffleType::Sattolo),
+            ("local", radius) => Ok(ShuffleType::Local { radius: radius.unwrap_or(Self::DEFAULT_RADIUS) }),
+            ("random_pairs", pairs) => Ok(ShuffleType::RandomPairs { pairs: pairs.unwrap_or(Self::DEFAULT_PAIRS) }),
+            ("reverse_runs", runs) => Ok(ShuffleType::ReverseRuns { runs: runs.unwrap_or(Self::DEFAULT_RUNS) }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq)]
 pub enum DisplayMode {
     Bars,
@@ -91,3 +292,42 @@ impl Default for DisplayMode {
         DisplayMode::Circle
     }
 }
+
+impl FromStr for DisplayMode {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "bars" => Ok(DisplayMode::Bars),
+            "pyramid" => Ok(DisplayMode::Pyramid),
+            "circle" => Ok(DisplayMode::Circle),
+            "doughnut" => Ok(DisplayMode::Doughnut),
+            "dots" => Ok(DisplayMode::Dots),
+            "spiral" => Ok(DisplayMode::Spiral),
+            "disparity_line" => Ok(DisplayMode::DisparityLine),
+            "disparity_loop" => Ok(DisplayMode::DisparityLoop),
+            "pixels" => Ok(DisplayMode::Pixels),
+            x => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid display mode: {}. Options are: bars, pyramid, circle, doughnut, dots, spiral, disparity_line, \
+disparity_loop, pixels", x)
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ArraySelector;
+
+    #[test]
+    fn array_selectors_parse_and_match() {
+        assert_eq!("all".parse::<ArraySelector>().unwrap(), ArraySelector::All);
+        assert_eq!(" 3 ".parse::<ArraySelector>().unwrap(), ArraySelector::Index(3));
+        assert!("first".parse::<ArraySelector>().is_err());
+
+        assert!(ArraySelector::All.matches(5));
+        assert!(ArraySelector::Index(2).matches(2));
+        assert!(!ArraySelector::Index(2).matches(5));
+    }
+}
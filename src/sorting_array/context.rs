@@ -0,0 +1,243 @@
+use std::cmp::Ordering;
+use std::sync::{
+    Arc, Mutex, RwLock, RwLockWriteGuard,
+    atomic::{AtomicUsize, Ordering as AtomicOrdering},
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{DataArrWrapper, highlight::Highlight, pool::SortPool, random::SortRng, trace::HeapShape};
+
+// Returned by every operation once the sort has been told to stop, so sorts can bail out with `?`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Stopped;
+
+pub type SortResult<T = ()> = Result<T, Stopped>;
+
+// Running totals of the work done by a sort. Shared between all threads of a sort.
+#[derive(Default, Debug)]
+pub struct SortCounters {
+    comparisons: AtomicUsize,
+    swaps: AtomicUsize,
+    reads: AtomicUsize,
+    writes: AtomicUsize,
+    timer: Mutex<SortTimer>,
+}
+
+#[derive(Default, Debug)]
+struct SortTimer {
+    started: Option<Instant>,
+    finished: Option<Duration>,     // Time taken, once the sort has finished
+}
+
+// Copy of the counters at a point in time.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CounterSnapshot {
+    pub comparisons: usize,
+    pub swaps: usize,
+    pub reads: usize,
+    pub writes: usize,
+    pub elapsed: Duration,
+}
+
+impl SortCounters {
+    pub fn reset(&self) {
+        self.comparisons.store(0, AtomicOrdering::Relaxed);
+        self.swaps.store(0, AtomicOrdering::Relaxed);
+        self.reads.store(0, AtomicOrdering::Relaxed);
+        self.writes.store(0, AtomicOrdering::Relaxed);
+        *self.timer.lock().unwrap() = SortTimer::default();
+    }
+
+    // Resets the counters and starts timing.
+    pub fn start(&self) {
+        self.reset();
+        self.timer.lock().unwrap().started = Some(Instant::now());
+    }
+
+    // Stops timing.
+    pub fn finish(&self) {
+        let mut timer = self.timer.lock().unwrap();
+        timer.finished = timer.started.map(|started| started.elapsed());
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        let timer = self.timer.lock().unwrap();
+        match (timer.started, timer.finished) {
+            (_, Some(finished)) => finished,
+            (Some(started), None) => started.elapsed(),
+            (None, None) => Duration::default(),
+        }
+    }
+
+    pub fn snapshot(&self) -> CounterSnapshot {
+        CounterSnapshot {
+            comparisons: self.comparisons.load(AtomicOrdering::Relaxed),
+            swaps: self.swaps.load(AtomicOrdering::Relaxed),
+            reads: self.reads.load(AtomicOrdering::Relaxed),
+            writes: self.writes.load(AtomicOrdering::Relaxed),
+            elapsed: self.elapsed(),
+        }
+    }
+}
+
+// Handle that sorts use to access the array. Takes care of locking, highlighting, counting, pacing and
+// stopping/pausing, so that each sort only has to describe the algorithm.
+// Compares, swaps and writes each sleep for the sort's sleep time and are a checkpoint for pausing/stepping.
+#[derive(Clone)]
+pub struct SortContext {
+    data: Arc<RwLock<DataArrWrapper>>,
+    counters: Arc<SortCounters>,
+    sleep_time: Duration,
+    pool: Arc<SortPool>,    // For multithreaded sorts
+    rng: Arc<SortRng>,
+}
+
+impl SortContext {
+    pub fn new(
+        data: Arc<RwLock<DataArrWrapper>>,
+        counters: Arc<SortCounters>,
+        sleep_time: Duration,
+        pool: Arc<SortPool>,
+        rng: Arc<SortRng>,
+    ) -> Self {
+        Self {
+            data,
+            counters,
+            sleep_time,
+            pool,
+            rng,
+        }
+    }
+
+    #[inline]
+    pub fn pool(&self) -> &SortPool {
+        &self.pool
+    }
+
+    // Random index in [low, high), from the seeded RNG.
+    #[inline]
+    pub fn random_range(&self, low: usize, high: usize) -> usize {
+        self.rng.range(low, high)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.read().unwrap().len()
+    }
+
+    // Sleeps, then waits while paused.
+    fn checkpoint(&self) -> SortResult {
+        thread::sleep(self.sleep_time);
+        self.check_for_stop()
+    }
+
+    // Waits while paused, without sleeping. For sorts to call in places that do not touch the array.
+    #[inline]
+    pub fn check_for_stop(&self) -> SortResult {
+        if DataArrWrapper::wait_while_paused(&self.data) {
+            Err(Stopped)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn read(&self, index: usize) -> SortResult<usize> {
+        let value = {
+            let read = self.data.read().unwrap();
+            if read.sorted {
+                return Err(Stopped);
+            }
+            read[index]
+        };
+        self.counters.reads.fetch_add(1, AtomicOrdering::Relaxed);
+        Ok(value)
+    }
+
+    // Copies the whole array out in one go (e.g. for radix sort's buckets). Counts as a read of every element.
+    pub fn read_all(&self) -> SortResult<Vec<usize>> {
+        let values = {
+            let read = self.data.read().unwrap();
+            if read.sorted {
+                return Err(Stopped);
+            }
+            read.arr.clone()
+        };
+        self.counters.reads.fetch_add(values.len(), AtomicOrdering::Relaxed);
+        Ok(values)
+    }
+
+    // Write access to the array, unless the sort has been stopped. Checked under the same lock as the access, so a
+    // stopped sort can't touch an array that has since been replaced.
+    fn write_lock(&self) -> SortResult<RwLockWriteGuard<'_, DataArrWrapper>> {
+        let write = self.data.write().unwrap();
+        if write.sorted {
+            Err(Stopped)
+        } else {
+            Ok(write)
+        }
+    }
+
+    pub fn write(&self, index: usize, value: usize) -> SortResult {
+        self.write_lock()?.set(index, value);
+        self.counters.writes.fetch_add(1, AtomicOrdering::Relaxed);
+        self.checkpoint()
+    }
+
+    pub fn swap(&self, a: usize, b: usize) -> SortResult {
+        self.write_lock()?.swap(a, b);
+        self.counters.swaps.fetch_add(1, AtomicOrdering::Relaxed);
+        self.checkpoint()
+    }
+
+    // Compares the elements at two indices.
+    pub fn compare(&self, a: usize, b: usize) -> SortResult<Ordering> {
+        let ordering = self.write_lock()?.compare(a, b);
+        self.counters.comparisons.fetch_add(1, AtomicOrdering::Relaxed);
+        self.checkpoint()?;
+        Ok(ordering)
+    }
+
+    // Compares the element at an index with a value the sort is holding onto.
+    pub fn compare_value(&self, index: usize, value: usize) -> SortResult<Ordering> {
+        let ordering = self.write_lock()?.compare_value(index, value);
+        self.counters.comparisons.fetch_add(1, AtomicOrdering::Relaxed);
+        self.checkpoint()?;
+        Ok(ordering)
+    }
+
+    // Moves the highlight's marker to an element. Does not count as an operation, so there is no sleep.
+    // Each thread of a sort has its own markers.
+    #[inline]
+    pub fn mark(&self, highlight: Highlight, index: usize) {
+        self.data.write().unwrap().set_marker(highlight, Some(index));
+    }
+
+    #[inline]
+    pub fn unmark(&self, highlight: Highlight) {
+        self.data.write().unwrap().set_marker(highlight, None);
+    }
+
+    // Highlights a range of elements. Like `mark`, there is no sleep.
+    #[inline]
+    pub fn mark_range(&self, highlight: Highlight, start: usize, len: usize) {
+        self.data.write().unwrap().highlight_range(highlight, start, len, true);
+    }
+
+    #[inline]
+    pub fn unmark_range(&self, highlight: Highlight, start: usize, len: usize) {
+        self.data.write().unwrap().highlight_range(highlight, start, len, false);
+    }
+
+    // Removes the highlight's marker and ranges (the ones made by this thread).
+    #[inline]
+    pub fn clear(&self, highlight: Highlight) {
+        self.data.write().unwrap().clear_highlight(highlight);
+    }
+
+    // Shows the first `len` elements as a heap with `arity` children per node, or clears it if None.
+    #[inline]
+    pub fn mark_heap(&self, heap: Option<HeapShape>) {
+        self.data.write().unwrap().set_heap(heap);
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::{self, ErrorKind};
+use std::str::FromStr;
+
+use super::random::SortRng;
+use crate::TWO_PI;
+
+// How the values of a new (or reset) array are generated. Used by the visualiser and the benchmarks.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Distribution {
+    Random,         // Every value from 0 to the length once, in a random order
+    FewUnique,
+    NearlySorted,
+    Reversed,
+    Sawtooth,
+    OrganPipe,
+    Gaussian,
+    Quadratic,
+    ManyDuplicates,
+    Sorted,
+}
+
+pub const ALL_DISTRIBUTIONS: [Distribution; 10] = [
+    Distribution::Random,
+    Distribution::FewUnique,
+    Distribution::NearlySorted,
+    Distribution::Reversed,
+    Distribution::Sawtooth,
+    Distribution::OrganPipe,
+    Distribution::Gaussian,
+    Distribution::Quadratic,
+    Distribution::ManyDuplicates,
+    Distribution::Sorted,
+];
+
+impl Distribution {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Distribution::Random => "random",
+            Distribution::FewUnique => "few_unique",
+            Distribution::NearlySorted => "nearly_sorted",
+            Distribution::Reversed => "reversed",
+            Distribution::Sawtooth => "sawtooth",
+            Distribution::OrganPipe => "organ_pipe",
+            Distribution::Gaussian => "gaussian",
+            Distribution::Quadratic => "quadratic",
+            Distribution::ManyDuplicates => "many_duplicates",
+            Distribution::Sorted => "sorted",
+        }
+    }
+
+    // The distribution after this one in ALL_DISTRIBUTIONS (or before it if `backwards`), wrapping around.
+    pub fn cycle(&self, backwards: bool) -> Self {
+        let len = ALL_DISTRIBUTIONS.len();
+        let position = ALL_DISTRIBUTIONS.iter().position(|distribution| distribution == self).unwrap();
+        let offset = if backwards { len - 1 } else { 1 };
+        ALL_DISTRIBUTIONS[(position + offset) % len]
+    }
+
+    // Values are between 0 and the length, but do not have to reach it, so the largest can be less than the length.
+    pub fn generate(&self, len: usize, rng: &SortRng) -> Vec<usize> {
+        const UNIQUE_VALUES: usize = 8;     // For FewUnique
+        const TEETH: usize = 4;             // For Sawtooth
+        const STANDARD_DEVIATIONS: f64 = 3.0;   // Number either side of the mean that fit in the array, for Gaussian
+
+        match self {
+            Distribution::Random => {
+                let mut values: Vec<usize> = (0..len).collect();
+                shuffle(&mut values, rng);
+                values
+            },
+            Distribution::FewUnique => {
+                let step = (len / UNIQUE_VALUES).max(1);
+                let mut values: Vec<usize> = (0..len).map(|i| (i % UNIQUE_VALUES) * step).collect();
+                shuffle(&mut values, rng);
+                values
+            },
+            Distribution::NearlySorted => {
+                // Swap a few elements with others close by.
+                let mut values: Vec<usize> = (0..len).collect();
+                if len > 1 {
+                    for _ in 0..(len / 20).max(1) {
+                        let i = rng.range(0, len);
+                        let j = (i + rng.range(1, 10)).min(len - 1);
+                        values.swap(i, j);
+                    }
+                }
+                values
+            },
+            Distribution::Reversed => (0..len).rev().collect(),
+            Distribution::Sawtooth => {
+                // Ascending teeth that each go from the bottom to the top.
+                let tooth_len = (len / TEETH).max(1);
+                (0..len).map(|i| (i % tooth_len) * len / tooth_len).collect()
+            },
+            Distribution::OrganPipe => {
+                // Ascends to the middle, then descends.
+                (0..len).map(|i| i.min(len - 1 - i) * 2).collect()
+            },
+            Distribution::Gaussian => {
+                let mean = len as f64 / 2.0;
+                let deviation = len as f64 / (2.0 * STANDARD_DEVIATIONS);
+                (0..len)
+                    .map(|_| {
+                        // Box-Muller transform
+                        let magnitude = (-2.0 * (1.0 - rng.unit()).ln()).sqrt();
+                        let normal = magnitude * (TWO_PI as f64 * rng.unit()).cos();
+                        (mean + normal * deviation).round().max(0.0).min(len.saturating_sub(1) as f64) as usize
+                    })
+                    .collect()
+            },
+            Distribution::Quadratic => {
+                // Mostly small values, with fewer and fewer larger ones.
+                let mut values: Vec<usize> = (0..len).map(|i| i * i / len).collect();
+                shuffle(&mut values, rng);
+                values
+            },
+            Distribution::ManyDuplicates => {
+                // Random values from only about sqrt(len) different ones.
+                let levels = ((len as f64).sqrt() as usize).max(1);
+                (0..len).map(|_| rng.range(0, levels) * len / levels).collect()
+            },
+            Distribution::Sorted => (0..len).collect(),
+        }
+    }
+}
+
+impl FromStr for Distribution {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        ALL_DISTRIBUTIONS.iter()
+            .find(|distribution| distribution.name() == s.to_lowercase())
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = ALL_DISTRIBUTIONS.iter().map(Distribution::name).collect();
+                io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("Invalid distribution: {}. Options are: {}", s, names.join(", "))
+                )
+            })
+    }
+}
+
+// Fisher-Yates shuffle, instantly rather than animated.
+pub fn shuffle(values: &mut [usize], rng: &SortRng) {
+    for i in (1..values.len()).rev() {
+        values.swap(i, rng.range(0, i + 1));
+    }
+}
+
+// Value that the display scales the array to. One more than the largest value, so that every element has some height.
+#[inline]
+pub fn max_value(values: &[usize]) -> usize {
+    values.iter().max().map_or(0, |max| max + 1)
+}
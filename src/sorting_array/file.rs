@@ -0,0 +1,138 @@
+// Loading arrays from, and saving them to, files. JSON files hold an array of integers. Anything else (CSV, newline
+// separated, etc.) holds integers separated by commas, whitespace or newlines, optionally with a header line.
+
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use yaml_rust::YamlLoader;
+
+// Values loaded from a file, kept so that resetting the array goes back to them.
+pub struct LoadedArray {
+    pub name: String,       // Shown in place of the distribution
+    pub values: Vec<usize>,
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+// Negative values are shifted up so that the smallest value is 0.
+pub fn load(path: &Path) -> io::Result<Vec<usize>> {
+    let text = fs::read_to_string(path)?;
+    let is_json = path.extension().map_or(false, |extension| extension.eq_ignore_ascii_case("json"));
+
+    let values = if is_json {
+        parse_json(&text)?
+    } else {
+        parse_separated(&text)?
+    };
+    shift_to_zero(values)
+}
+
+// File name shown in place of the distribution.
+pub fn display_name(path: &Path) -> String {
+    path.file_name().map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy()).into_owned()
+}
+
+// array_<time>.csv in the working directory, so that earlier saves are kept.
+#[inline]
+pub fn new_save_path() -> PathBuf {
+    timestamped_path("array", "csv")
+}
+
+// <name>_<time>.<extension> in the working directory.
+pub fn timestamped_path(name: &str, extension: &str) -> PathBuf {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    PathBuf::from(format!("{}_{}.{}", name, time, extension))
+}
+
+// Saves one value per line, which can be loaded again as CSV.
+pub fn save(path: &Path, values: &[usize]) -> io::Result<()> {
+    let mut text = String::with_capacity(values.len() * 4);
+    for value in values {
+        text.push_str(&value.to_string());
+        text.push('\n');
+    }
+    fs::write(path, text)
+}
+
+// JSON is a subset of YAML, so the config's YAML parser reads it.
+pub fn parse_json(text: &str) -> io::Result<Vec<i64>> {
+    let docs = YamlLoader::load_from_str(text)
+        .map_err(|err| invalid(format!("Could not parse JSON: {}", err)))?;
+
+    docs.first()
+        .and_then(|doc| doc.as_vec())
+        .ok_or_else(|| invalid(String::from("JSON file should contain an array of integers.")))?
+        .iter()
+        .enumerate()
+        .map(|(i, value)| value.as_i64().ok_or_else(|| invalid(format!("Element {} of the JSON array is not an integer.", i))))
+        .collect()
+}
+
+pub fn parse_separated(text: &str) -> io::Result<Vec<i64>> {
+    let mut values = Vec::new();
+
+    for (line_num, line) in text.lines().enumerate() {
+        let tokens: Vec<&str> = line.split(|c: char| c == ',' || c.is_whitespace())
+            .map(|token| token.trim_matches('"'))
+            .filter(|token| !token.is_empty())
+            .collect();
+
+        match tokens.iter().map(|token| token.parse::<i64>()).collect::<Result<Vec<i64>, _>>() {
+            Ok(line_values) => values.extend(line_values),
+            Err(_) if values.is_empty() && line_num == 0 => (),    // Header
+            Err(_) => return Err(invalid(format!("Line {} contains something that is not an integer: {}", line_num + 1, line))),
+        }
+    }
+    Ok(values)
+}
+
+// Fails if the values are too far apart to be shifted into a usize. The largest value is kept below usize::MAX, since the
+// array's max_val is one more than it.
+pub fn shift_to_zero(values: Vec<i64>) -> io::Result<Vec<usize>> {
+    let min = values.iter().copied().min().unwrap_or(0).min(0);
+    values.into_iter()
+        .map(|value| {
+            usize::try_from(value as i128 - min as i128).ok()
+                .filter(|&shifted| shifted < usize::MAX)
+                .ok_or_else(|| invalid(format!("{} and {} are too far apart to be loaded together.", min, value)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn array_files_parse_in_every_format() {
+        let expected = vec![3, 1, 4, 1, 5];
+
+        assert_eq!(parse_separated("3\n1\n4\n1\n5\n").unwrap(), expected);
+        assert_eq!(parse_separated("3, 1, 4,\n1, 5").unwrap(), expected);
+        assert_eq!(parse_separated("value\n3\n1\n4\n1\n5").unwrap(), expected);     // Header
+        assert_eq!(parse_json("[3, 1, 4, 1, 5]").unwrap(), expected);
+
+        assert!(parse_separated("3\n1\nfour").is_err());
+        assert!(parse_json("{\"values\": [3, 1]}").is_err());
+        assert!(parse_json("[3, 1.5]").is_err());
+
+        assert_eq!(shift_to_zero(vec![-2, 0, 3]).unwrap(), vec![0, 2, 5]);
+        assert_eq!(shift_to_zero(vec![i64::MIN, 0]).unwrap(), vec![0, i64::MAX as usize + 1]);
+        assert!(shift_to_zero(vec![i64::MIN, i64::MAX]).is_err());
+    }
+
+    #[test]
+    fn saved_arrays_load_the_same() {
+        let path = std::env::temp_dir().join(format!("sort_visualiser_test_{}.csv", std::process::id()));
+        let values = vec![9, 0, 7, 2, 2, 5];
+        save(&path, &values).unwrap();
+        let loaded = load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, values);
+    }
+}
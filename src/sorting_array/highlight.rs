@@ -0,0 +1,202 @@
+use std::fmt;
+
+use crate::tools;
+
+// What a highlight means, which decides the colour it is drawn in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColourRole {
+    Active,     // Elements being compared or moved
+    Pivot,      // Elements the sort is holding onto or comparing against
+    Boundary,   // Edge of a region, e.g. the end of a heap
+    Range,      // Region being worked on, e.g. a run or a merge
+    Bucket,     // Group of elements, e.g. a radix sort bucket
+}
+
+impl ColourRole {
+    // `owner` is the thread that made the highlight (see pool::highlight_owner), so that each worker of a
+    // multithreaded sort gets its own colours. `index` is the position of a range among the other ranges of the same
+    // highlight, so that neighbouring ranges can be told apart.
+    pub fn colour(self, owner: usize, index: usize) -> (f32, f32, f32) {
+        match self {
+            ColourRole::Active if owner == 0 => (0.0, 0.2, 1.0),                // Blue
+            ColourRole::Pivot if owner == 0 => (0.8516, 0.4023, 0.8945),        // Purple
+            ColourRole::Active => tools::hsv_to_rgb(worker_hue(owner), 1.0, 1.0),
+            ColourRole::Pivot => tools::hsv_to_rgb(worker_hue(owner), 0.45, 1.0),
+            ColourRole::Boundary => (1.0, 1.0, 1.0),
+            ColourRole::Range if index % 2 == 0 => (1.0, 1.0, 1.0),
+            ColourRole::Range => (0.5, 0.5, 0.5),
+            ColourRole::Bucket => tools::hsv_to_rgb(spread_hue(index), 0.6, 1.0),
+        }
+    }
+}
+
+// Hues from cyan to pink, staying clear of the red -> green used for the elements.
+fn spread_hue(i: usize) -> f32 {
+    const GOLDEN_RATIO_CONJUGATE: f32 = 0.618_034;  // Spreads out the hues of consecutive numbers
+    0.45 + (i as f32 * GOLDEN_RATIO_CONJUGATE).fract() * 0.5
+}
+
+#[inline]
+fn worker_hue(owner: usize) -> f32 {
+    spread_hue(owner - 1)
+}
+
+// A named highlight that sorts can place markers and ranges with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Highlight {
+    pub name: &'static str,
+    pub role: ColourRole,
+}
+
+// Highlights used by the built in sorts. Sorts can make their own, with any name.
+pub const ACTIVE: Highlight = Highlight { name: "active", role: ColourRole::Active };
+pub const ACTIVE_2: Highlight = Highlight { name: "active 2", role: ColourRole::Active };
+pub const PIVOT: Highlight = Highlight { name: "pivot", role: ColourRole::Pivot };
+pub const HEAP_END: Highlight = Highlight { name: "heap end", role: ColourRole::Boundary };
+pub const RUN: Highlight = Highlight { name: "run", role: ColourRole::Range };
+pub const MERGE: Highlight = Highlight { name: "merge", role: ColourRole::Range };
+pub const BUCKET: Highlight = Highlight { name: "bucket", role: ColourRole::Bucket };
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mark {
+    Marker(usize),      // A single element
+    Range {
+        start: usize,
+        len: usize,
+    },
+}
+
+impl fmt::Display for Mark {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Mark::Marker(index) => write!(f, "[{}]", index),
+            Mark::Range { start, len } => write!(f, "[{}..{}]", start, start + len),
+        }
+    }
+}
+
+// Everything one thread has marked with a highlight. A highlight has at most one marker per thread (marking
+// another element moves it), but can have any number of ranges.
+#[derive(Clone, Debug)]
+pub struct HighlightSet {
+    pub highlight: Highlight,
+    pub owner: usize,
+    pub marks: Vec<Mark>,
+}
+
+// A range to draw, with the row of strips it goes in (one row for each highlight that has ranges).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DrawnRange {
+    pub row: usize,
+    pub start: usize,
+    pub len: usize,
+    pub colour: (f32, f32, f32),
+}
+
+// All of the highlights on an array.
+#[derive(Clone, Debug, Default)]
+pub struct Highlights {
+    sets: Vec<HighlightSet>,
+}
+
+impl Highlights {
+    // Everything marked, by every thread.
+    #[inline]
+    pub fn sets(&self) -> &[HighlightSet] {
+        &self.sets
+    }
+
+    #[inline]
+    fn find(&self, owner: usize, highlight: Highlight) -> Option<&HighlightSet> {
+        self.sets.iter().find(|set| set.owner == owner && set.highlight == highlight)
+    }
+
+    // Marks of a highlight made by a thread.
+    pub fn marks(&self, owner: usize, highlight: Highlight) -> &[Mark] {
+        self.find(owner, highlight).map_or(&[], |set| &set.marks)
+    }
+
+    pub fn marker(&self, owner: usize, highlight: Highlight) -> Option<usize> {
+        self.marks(owner, highlight).iter().find_map(|mark| match *mark {
+            Mark::Marker(index) => Some(index),
+            _ => None,
+        })
+    }
+
+    // Replaces the old mark with the new one. Either can be None, to just add or remove a mark. Setting or clearing a
+    // marker replaces whatever marker the highlight had, so that replaying this backwards always puts the old one back.
+    pub fn change(&mut self, owner: usize, highlight: Highlight, old: Option<Mark>, new: Option<Mark>) {
+        let position = match self.sets.iter().position(|set| set.owner == owner && set.highlight == highlight) {
+            Some(position) => position,
+            None if new.is_some() => {
+                self.sets.push(HighlightSet { highlight, owner, marks: Vec::new() });
+                self.sets.len() - 1
+            },
+            None => return,
+        };
+
+        let marks = &mut self.sets[position].marks;
+        match (old, new) {
+            (Some(Mark::Marker(_)), _) | (_, Some(Mark::Marker(_))) => {
+                marks.retain(|mark| !matches!(mark, Mark::Marker(_)));
+            },
+            (Some(old), _) => {
+                if let Some(i) = marks.iter().position(|&mark| mark == old) {
+                    marks.remove(i);
+                }
+            },
+            (None, _) => (),
+        }
+        if let Some(new) = new {
+            marks.push(new);
+        }
+
+        if marks.is_empty() {
+            self.sets.remove(position);
+        }
+    }
+
+    // The element marked with the highlight, by any thread.
+    pub fn first_marker(&self, highlight: Highlight) -> Option<usize> {
+        self.sets.iter()
+            .filter(|set| set.highlight == highlight)
+            .find_map(|set| self.marker(set.owner, highlight))
+    }
+
+    // Colour of the element if it has a marker on it. If it has several, roles earlier in ColourRole win.
+    pub fn colour(&self, index: usize) -> Option<(f32, f32, f32)> {
+        self.sets.iter()
+            .filter(|set| set.marks.contains(&Mark::Marker(index)))
+            .min_by_key(|set| set.highlight.role)
+            .map(|set| set.highlight.role.colour(set.owner, 0))
+    }
+
+    pub fn ranges(&self) -> Vec<DrawnRange> {
+        let mut drawn = Vec::new();
+        let mut row = 0;
+
+        for set in self.sets.iter() {
+            let mut ranges: Vec<(usize, usize)> = set.marks.iter()
+                .filter_map(|mark| match *mark {
+                    Mark::Range { start, len } => Some((start, len)),
+                    _ => None,
+                })
+                .collect();
+            if ranges.is_empty() {
+                continue;
+            }
+            ranges.sort_unstable();
+
+            for (i, (start, len)) in ranges.into_iter().enumerate() {
+                drawn.push(DrawnRange {
+                    row,
+                    start,
+                    len,
+                    colour: set.highlight.role.colour(set.owner, i),
+                });
+            }
+            row += 1;
+        }
+        drawn
+    }
+}
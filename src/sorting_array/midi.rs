@@ -0,0 +1,129 @@
+// Standard MIDI Files, for arranging the sound of sorts in other music software. Each sound becomes a note on one
+// track (format 0), at the nearest key to its pitch.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const TICKS_PER_BEAT: u16 = 480;
+const MICROS_PER_BEAT: u32 = 500_000;   // 120 beats per minute
+const VELOCITY: u8 = 100;
+const CHANNEL: u8 = 0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Note {
+    pub start: f64,     // Seconds from the start of the file
+    pub length: f64,    // Seconds
+    pub key: u8,        // MIDI key, 60 being middle C
+}
+
+// Nearest MIDI key to a frequency, with A4 (440Hz) being 69.
+pub fn key_from_hz(hz: f64) -> u8 {
+    (69.0 + 12.0 * (hz / 440.0).log2()).round().clamp(0.0, 127.0) as u8
+}
+
+#[inline]
+fn ticks(seconds: f64) -> u64 {
+    (seconds * 1_000_000.0 / MICROS_PER_BEAT as f64 * TICKS_PER_BEAT as f64).round() as u64
+}
+
+// Variable length quantity: 7 bits per byte, most significant first, with the top bit set on all but the last byte.
+fn write_vlq(bytes: &mut Vec<u8>, mut value: u64) {
+    let mut groups = vec![(value & 0x7F) as u8];
+    value >>= 7;
+    while value > 0 {
+        groups.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.extend(groups.iter().rev());
+}
+
+// The whole file. Notes can be in any order. A note that starts while another on the same key is still playing ends
+// that one, since MIDI can only have one of each key playing on a channel.
+pub fn encode(notes: &[Note]) -> Vec<u8> {
+    let mut notes = notes.to_vec();
+    notes.sort_by_key(|note| (ticks(note.start), note.key));
+    notes.dedup_by_key(|note| (ticks(note.start), note.key));  // The same key twice at once is played once
+
+    // (tick, is note on, key). Note offs go before note ons at the same tick, so a repeated key is played again.
+    let mut events: Vec<(u64, bool, u8)> = Vec::with_capacity(notes.len() * 2);
+    let mut next_starts = [None; 128];     // Tick that each key is next played at, going backwards through the notes
+    for note in notes.iter().rev() {
+        let start = ticks(note.start);
+        let end = ticks(note.start + note.length).max(start + 1);
+        let next_start: Option<u64> = next_starts[note.key as usize];
+
+        events.push((start, true, note.key));
+        events.push((next_start.map_or(end, |next_start| end.min(next_start)), false, note.key));
+        next_starts[note.key as usize] = Some(start);
+    }
+    events.sort_unstable();
+
+    let mut track = Vec::new();
+    write_vlq(&mut track, 0);
+    track.extend(&[0xFF, 0x51, 0x03]);     // Tempo
+    track.extend(&MICROS_PER_BEAT.to_be_bytes()[1..]);
+
+    let mut last_tick = 0;
+    for (tick, on, key) in events {
+        write_vlq(&mut track, tick - last_tick);
+        last_tick = tick;
+        if on {
+            track.extend(&[0x90 | CHANNEL, key, VELOCITY]);
+        } else {
+            track.extend(&[0x80 | CHANNEL, key, 0]);
+        }
+    }
+    write_vlq(&mut track, 0);
+    track.extend(&[0xFF, 0x2F, 0x00]);     // End of track
+
+    let mut bytes = Vec::with_capacity(track.len() + 22);
+    bytes.extend(b"MThd");
+    bytes.extend(&6u32.to_be_bytes());
+    bytes.extend(&0u16.to_be_bytes());     // Format 0: a single track
+    bytes.extend(&1u16.to_be_bytes());     // Number of tracks
+    bytes.extend(&TICKS_PER_BEAT.to_be_bytes());
+    bytes.extend(b"MTrk");
+    bytes.extend(&(track.len() as u32).to_be_bytes());
+    bytes.extend(track);
+    bytes
+}
+
+pub fn save(path: &Path, notes: &[Note]) -> io::Result<()> {
+    fs::write(path, encode(notes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn midi_files_have_a_note_for_every_sound() {
+        assert_eq!(key_from_hz(440.0), 69);
+        assert_eq!(key_from_hz(261.63), 60);     // Middle C
+        assert_eq!(key_from_hz(130.81), 48);
+
+        // At 120 beats per minute and 480 ticks per beat, a second is 960 ticks.
+        let notes = [
+            Note { start: 0.0, length: 0.1, key: 60 },
+            Note { start: 0.05, length: 0.1, key: 60 },     // Cuts off the first note
+            Note { start: 0.05, length: 0.1, key: 64 },
+        ];
+        let bytes = encode(&notes);
+
+        assert_eq!(&bytes[..14], &[b'M', b'T', b'h', b'd', 0, 0, 0, 6, 0, 0, 0, 1, 0x01, 0xE0]);
+        assert_eq!(&bytes[14..18], b"MTrk");
+        let track = &bytes[22..];
+        assert_eq!(u32::from_be_bytes([bytes[18], bytes[19], bytes[20], bytes[21]]) as usize, track.len());
+        assert_eq!(track, &[
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20,   // Tempo
+            0x00, 0x90, 60, 100,
+            0x30, 0x80, 60, 0,      // 48 ticks later
+            0x00, 0x90, 60, 100,
+            0x00, 0x90, 64, 100,
+            0x60, 0x80, 60, 0,      // 96 ticks later
+            0x00, 0x80, 64, 0,
+            0x00, 0xFF, 0x2F, 0x00,
+        ][..]);
+    }
+}
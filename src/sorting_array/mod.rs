@@ -1,6 +1,18 @@
 mod array;
 mod commands;
+mod shuffles;
 mod sorts;
+pub mod algorithms;
+pub mod context;
+pub mod distribution;
+pub mod file;
+pub mod highlight;
+pub mod midi;
+pub mod pool;
+pub mod random;
+pub mod trace;
 pub mod audio;
+#[cfg(test)]
+pub(crate) mod tests;
 pub use array::*;
 pub use commands::*;
\ No newline at end of file
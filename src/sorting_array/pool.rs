@@ -0,0 +1,98 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use super::context::SortResult;
+
+thread_local! {
+    // How many pool tasks the current thread is inside of, so that a worker running nested tasks is only counted once.
+    static TASK_DEPTH: Cell<usize> = Cell::new(0);
+}
+
+// Which set of highlights the current thread uses: 0 for a sort's own thread, then one for each pool worker.
+#[inline]
+pub fn highlight_owner() -> usize {
+    rayon::current_thread_index().map_or(0, |worker| worker + 1)
+}
+
+// Bounded work-stealing pool that the multithreaded sorts split their work across, rather than starting a thread
+// for every level of recursion. Each array has its own, since a paused sort keeps hold of the workers it is running on.
+pub struct SortPool {
+    pool: ThreadPool,
+    cutoff_depth: usize,    // Recursion depth after which sorts stop splitting their work
+    busy: AtomicUsize,      // Workers currently running part of a sort
+}
+
+impl SortPool {
+    // 0 workers means one per CPU.
+    pub fn new(workers: usize, cutoff_depth: usize) -> Self {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(workers)
+            .thread_name(|i| format!("sort-worker-{}", i))
+            .build()
+            .expect("Could not start sort worker threads.");
+
+        Self {
+            pool,
+            cutoff_depth,
+            busy: AtomicUsize::new(0),
+        }
+    }
+
+    // Another pool with the same number of workers and cutoff depth.
+    #[inline]
+    pub fn new_like(&self) -> Self {
+        Self::new(self.workers(), self.cutoff_depth)
+    }
+
+    #[inline]
+    pub fn workers(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    #[inline]
+    pub fn busy_workers(&self) -> usize {
+        self.busy.load(AtomicOrdering::Relaxed)
+    }
+
+    // Runs a multithreaded sort on the pool. Blocks until it has finished.
+    pub fn install(&self, sort: impl FnOnce() -> SortResult + Send) -> SortResult {
+        self.pool.install(|| self.track(sort))
+    }
+
+    // Runs both halves of a sort, in parallel if `depth` is below the cutoff depth. Must be called from inside `install`.
+    pub fn join(
+        &self,
+        depth: usize,
+        a: impl FnOnce() -> SortResult + Send,
+        b: impl FnOnce() -> SortResult + Send,
+    ) -> SortResult {
+        if depth < self.cutoff_depth {
+            let (a, b) = rayon::join(|| self.track(a), || self.track(b));
+            a.and(b)
+        } else {
+            a()?;
+            b()
+        }
+    }
+
+    // Counts the current worker as busy while the task runs.
+    fn track(&self, task: impl FnOnce() -> SortResult) -> SortResult {
+        let outermost = TASK_DEPTH.with(|depth| {
+            depth.set(depth.get() + 1);
+            depth.get() == 1
+        });
+        if outermost {
+            self.busy.fetch_add(1, AtomicOrdering::Relaxed);
+        }
+
+        let result = task();
+
+        TASK_DEPTH.with(|depth| depth.set(depth.get() - 1));
+        if outermost {
+            self.busy.fetch_sub(1, AtomicOrdering::Relaxed);
+        }
+        result
+    }
+}
@@ -0,0 +1,52 @@
+use std::sync::{
+    Mutex,
+    atomic::{AtomicU64, Ordering as AtomicOrdering},
+};
+
+use nannou::rand::{self, Rng, SeedableRng, rngs::StdRng};
+
+// Source of all randomness (shuffles, random pivots and distributions), shared by every array so that a run can be
+// repeated from its seed. Multithreaded sorts draw from it in whatever order their workers get to it, so only
+// single threaded sorts are repeated exactly.
+pub struct SortRng {
+    seed: AtomicU64,
+    rng: Mutex<StdRng>,
+}
+
+impl SortRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed: AtomicU64::new(seed),
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed.load(AtomicOrdering::Relaxed)
+    }
+
+    // Starts again from the beginning of the seed's sequence.
+    pub fn reseed(&self, seed: u64) {
+        self.seed.store(seed, AtomicOrdering::Relaxed);
+        *self.rng.lock().unwrap() = StdRng::seed_from_u64(seed);
+    }
+
+    // Random number in [low, high)
+    #[inline]
+    pub fn range(&self, low: usize, high: usize) -> usize {
+        self.rng.lock().unwrap().gen_range(low, high)
+    }
+
+    // Random number in [0, 1)
+    #[inline]
+    pub fn unit(&self) -> f64 {
+        self.rng.lock().unwrap().gen()
+    }
+}
+
+// For when no seed is given.
+#[inline]
+pub fn random_seed() -> u64 {
+    rand::random()
+}
@@ -0,0 +1,76 @@
+use super::{
+    commands::ShuffleType,
+    context::{SortContext, SortResult},
+    highlight::{ACTIVE, ACTIVE_2},
+};
+
+// Shuffles are animated like sorts, with each swap going through the context.
+pub fn shuffle(ctx: &SortContext, shuffle_type: ShuffleType) -> SortResult {
+    let len = ctx.len();
+    if len < 2 {
+        return Ok(());
+    }
+
+    match shuffle_type {
+        ShuffleType::FisherYates => fisher_yates(ctx, len),
+        ShuffleType::Sattolo => sattolo(ctx, len),
+        ShuffleType::Local { radius } => local(ctx, len, radius),
+        ShuffleType::RandomPairs { pairs } => random_pairs(ctx, len, pairs),
+        ShuffleType::ReverseRuns { runs } => reverse_runs(ctx, len, runs),
+    }
+}
+
+#[inline]
+fn swap(ctx: &SortContext, a: usize, b: usize) -> SortResult {
+    ctx.mark(ACTIVE, a);
+    ctx.mark(ACTIVE_2, b);
+    ctx.swap(a, b)
+}
+
+// Every permutation is equally likely.
+fn fisher_yates(ctx: &SortContext, len: usize) -> SortResult {
+    for i in (1..len).rev() {
+        swap(ctx, i, ctx.random_range(0, i + 1))?;
+    }
+    Ok(())
+}
+
+// Fisher-Yates, but never swapping an element with itself, giving a random permutation that is a single cycle.
+fn sattolo(ctx: &SortContext, len: usize) -> SortResult {
+    for i in (1..len).rev() {
+        swap(ctx, i, ctx.random_range(0, i))?;
+    }
+    Ok(())
+}
+
+// Fisher-Yates going forwards, only picking from the next `radius` elements, so elements stay roughly where they were.
+fn local(ctx: &SortContext, len: usize, radius: usize) -> SortResult {
+    for i in 0..len - 1 {
+        swap(ctx, i, ctx.random_range(i, (i + radius + 1).min(len)))?;
+    }
+    Ok(())
+}
+
+fn random_pairs(ctx: &SortContext, len: usize, pairs: usize) -> SortResult {
+    for _ in 0..pairs {
+        swap(ctx, ctx.random_range(0, len), ctx.random_range(0, len))?;
+    }
+    Ok(())
+}
+
+fn reverse_runs(ctx: &SortContext, len: usize, runs: usize) -> SortResult {
+    let max_run_len = (len / runs.max(1)).max(2);
+
+    for _ in 0..runs {
+        let run_len = ctx.random_range(2, max_run_len + 1);
+        let mut lo = ctx.random_range(0, len - run_len + 1);
+        let mut hi = lo + run_len - 1;
+
+        while lo < hi {
+            swap(ctx, lo, hi)?;
+            lo += 1;
+            hi -= 1;
+        }
+    }
+    Ok(())
+}
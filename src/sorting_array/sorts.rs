@@ -1,127 +1,91 @@
-use super::DataArrWrapper;
+use super::{context::{SortContext, SortResult}, highlight::{self, ACTIVE, ACTIVE_2, PIVOT}, trace::HeapShape};
 
-use std::sync::{Arc, RwLock};
-use std::thread;
-use std::time::Duration;
-
-macro_rules! check_for_stop {
-    // Returns from function if sorted
-    ($data_arc:expr) => {
-        if $data_arc.read().unwrap().sorted {
-            return;
-        }
-    };
-}
-
-macro_rules! check_for_stop_break {
-    // Only does a break, so exits loop.
-    ($data_arc:expr) => {
-        if $data_arc.read().unwrap().sorted {
-            break;
-        }
-    };
-}
+use std::cmp::Ordering;
 
 // Shared by bubble sort and cocktail shaker sort.
 macro_rules! bubble {
-    ($data_arc:expr, $swapped:expr, $i:expr, $sleep_time:expr) => {
-        $data_arc.write().unwrap().set_active($i + 1);
+    ($ctx:expr, $swapped:expr, $i:expr) => {
+        $ctx.mark(ACTIVE, $i + 1);
 
-        let (d1, d2) = {
-            let read = $data_arc.read().unwrap();
-            (read[$i], read[$i + 1])
-        };
-        if d1 > d2 {
-            {
-                let mut data_write = $data_arc.write().unwrap();
-                data_write.swap($i, $i + 1);
-            }
+        if $ctx.compare($i, $i + 1)? == Ordering::Greater {
+            $ctx.swap($i, $i + 1)?;
             $swapped = true;
-            thread::sleep(*$sleep_time);
         }
     };
 }
 
-pub fn bubble_sort(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: &Duration) {
-    let len = data_arc.read().unwrap().len();
+pub fn bubble_sort(ctx: &SortContext) -> SortResult {
+    let len = ctx.len();
     let mut swapped = true;
 
-    while swapped && !data_arc.read().unwrap().sorted {
+    while swapped {
         swapped = false;
 
-        for i in 0..len - 1 {
-            check_for_stop!(data_arc);
-            bubble!(data_arc, swapped, i, sleep_time);
+        for i in 0..len.saturating_sub(1) {
+            bubble!(ctx, swapped, i);
         }
     }
+    Ok(())
 }
 
-pub fn cocktail_shaker_sort(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: &Duration) {
-    let len = data_arc.read().unwrap().len();
+pub fn cocktail_shaker_sort(ctx: &SortContext) -> SortResult {
+    let len = ctx.len();
     let mut swapped = true; // if an element was swapped
 
-    while swapped && !data_arc.read().unwrap().sorted {
+    while swapped {
         swapped = false;
 
-        for i in 0..len - 1 {
-            check_for_stop!(data_arc);
-            bubble!(data_arc, swapped, i, sleep_time);
+        for i in 0..len.saturating_sub(1) {
+            bubble!(ctx, swapped, i);
         }
 
         if swapped {
             swapped = false;
-            for i in (1..len - 1).rev() {
-                check_for_stop!(data_arc);
-                bubble!(data_arc, swapped, i, sleep_time);
+            for i in (1..len.saturating_sub(1)).rev() {
+                bubble!(ctx, swapped, i);
             }
         }
     }
+    Ok(())
 }
 
-pub fn insertion_sort(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: &Duration, start: usize, end: usize) { // end is inclusive
+pub fn insertion_sort(ctx: &SortContext, start: usize, end: usize) -> SortResult { // end is inclusive
     for i in start..end+1 {
-        check_for_stop!(data_arc);
-        data_arc.write().unwrap().set_pivot(i);
+        ctx.mark(PIVOT, i);
 
         for j in (start+1..=i).rev() {
-            data_arc.write().unwrap().set_active(j);
-            {
-                let read = data_arc.read().unwrap();
-                if read.sorted || read[j - 1] < read[j] {
-                    break;
-                }
+            ctx.mark(ACTIVE, j);
+            if ctx.compare(j - 1, j)? != Ordering::Greater {   // Don't move past equal elements, so the sort is stable
+                break;
             }
-            data_arc.write().unwrap().swap(j, j - 1);
-            thread::sleep(*sleep_time);
+            ctx.swap(j, j - 1)?;
         }
     }
+    Ok(())
 }
 
-pub fn selection_sort(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: &Duration) {
-    let len = data_arc.read().unwrap().len();
+pub fn selection_sort(ctx: &SortContext) -> SortResult {
+    let len = ctx.len();
 
-    for done in 0..len-1 {
-        check_for_stop!(data_arc);
-        data_arc.write().unwrap().set_active_2(done);
+    for done in 0..len.saturating_sub(1) {
+        ctx.mark(ACTIVE_2, done);
 
-        let mut min = (done, data_arc.read().unwrap()[done]); // (index, value) of minumum value in current part of list
+        let mut min = done; // Index of minumum value in current part of list
         for i in done+1..len {
-            check_for_stop!(data_arc);
-            data_arc.write().unwrap().set_active(i);
-            let val = data_arc.read().unwrap()[i];
-            if val < min.1 {    // If value less than curent minimum
-                min = (i, val);
-                data_arc.write().unwrap().set_pivot(i);
+            ctx.mark(ACTIVE, i);
+            if ctx.compare(i, min)? == Ordering::Less {    // If value less than curent minimum
+                min = i;
+                ctx.mark(PIVOT, i);
             }
-            thread::sleep(*sleep_time);
         }
 
         // Swap minumum with element at done
-        data_arc.write().unwrap().swap(min.0, done);
+        ctx.swap(min, done)?;
     }
+    Ok(())
 }
 
-pub fn shell_sort(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: &Duration) {
+pub fn shell_sort(ctx: &SortContext) -> SortResult {
     pub struct ShellSortGapsIter {
         // Iterator to generate gaps
         count: usize,
@@ -145,36 +109,85 @@ pub fn shell_sort(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: &Duration)
         }
     }
 
-    let len = data_arc.read().unwrap().len();
+    let len = ctx.len();
 
     let gaps: Vec<usize> = ShellSortGapsIter::default()
         .take_while(|i| *i < len)
         .collect();
 
     for gap in gaps.into_iter().rev() {
-        check_for_stop!(data_arc);
         for i in gap..len {
-            check_for_stop!(data_arc.clone());
-            let temp = data_arc.read().unwrap()[i];
+            let temp = ctx.read(i)?;
 
             let mut j = i;
-            while j >= gap && data_arc.read().unwrap()[j - gap] > temp {
-                {
-                    let mut write = data_arc.write().unwrap();
-                    write.set_active(j - gap);
-                    write.set_active_2(j);
-                    write[j] = write[j - gap];
-                }
+            while j >= gap && ctx.compare_value(j - gap, temp)? == Ordering::Greater {
+                ctx.mark(ACTIVE, j - gap);
+                ctx.mark(ACTIVE_2, j);
+                let shifted = ctx.read(j - gap)?;
+                ctx.write(j, shifted)?;
 
                 j -= gap;
-                thread::sleep(*sleep_time);
             }
-            data_arc.write().unwrap()[j] = temp;
+            if j != i {
+                ctx.write(j, temp)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Heap sort with `arity` children per node (2 for a binary heap, 3 for ternary).
+pub fn heap_sort(ctx: &SortContext, arity: usize) -> SortResult {
+    // Moves the element at root down the heap until it is larger than all of its children.
+    fn sift_down(ctx: &SortContext, mut root: usize, len: usize, arity: usize) -> SortResult {
+        loop {
+            let first_child = root * arity + 1;
+            if first_child >= len {
+                return Ok(());
+            }
+            ctx.mark(PIVOT, root);
+
+            let mut largest = root;
+            for child in first_child..(first_child + arity).min(len) {
+                ctx.mark(ACTIVE, largest);
+                ctx.mark(ACTIVE_2, child);
+                if ctx.compare(child, largest)? == Ordering::Greater {
+                    largest = child;
+                }
+            }
+
+            if largest == root {
+                return Ok(());
+            }
+            ctx.swap(root, largest)?;
+            root = largest;
         }
     }
+
+    let len = ctx.len();
+    if len < 2 {
+        return Ok(());
+    }
+
+    // Build the heap, starting from the last parent
+    ctx.mark_heap(Some(HeapShape { len, arity }));
+    for root in (0..=(len - 2)/arity).rev() {
+        sift_down(ctx, root, len, arity)?;
+    }
+
+    // Move the largest element to the end and shrink the heap
+    for end in (1..len).rev() {
+        ctx.swap(0, end)?;
+        ctx.mark_heap(Some(HeapShape { len: end, arity }));
+        ctx.mark(highlight::HEAP_END, end);
+        sift_down(ctx, 0, end, arity)?;
+    }
+    ctx.mark_heap(None);
+    ctx.unmark(highlight::HEAP_END);
+    Ok(())
 }
 
-pub fn radix_lsd(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: &Duration, base: usize) {
+pub fn radix_lsd(ctx: &SortContext, base: usize) -> SortResult {
     use radix::RadixNum;
     use std::collections::HashMap;
 
@@ -202,276 +215,631 @@ pub fn radix_lsd(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: &Duration, b
     }
 
     let (largest_digits, array_len) = {
-        let data_read = data_arc.read().unwrap();
-        (get_max_digits(&data_read, base), data_read.len())
+        let values = ctx.read_all()?;
+        (get_max_digits(&values, base), values.len())
     };
 
     for digit_num in 0..largest_digits {
         // Counting sort
         let mut buckets: HashMap<usize, Vec<usize>> = HashMap::new();
 
-        {
-            let data_read = data_arc.read().unwrap();
-
-            for num in data_read.iter() {
-                let digit = get_digit_at(*num, digit_num, base);
-                let bucket = buckets
-                    .entry(digit)
-                    .or_insert_with(|| Vec::with_capacity(array_len));
+        for num in ctx.read_all()? {
+            let digit = get_digit_at(num, digit_num, base);
+            let bucket = buckets
+                .entry(digit)
+                .or_insert_with(|| Vec::with_capacity(array_len));
 
-                bucket.push(*num);
-            }
+            bucket.push(num);
         }
 
         let mut i = 0;
         for key in 0..base {
             if let Some(bucket) = buckets.get(&key) {
+                ctx.mark_range(highlight::BUCKET, i, bucket.len());
                 for element in bucket.iter() {
-                    {
-                        let mut write = data_arc.write().unwrap();
-                        write.set_active(i);
-                        write[i] = *element;
-                        if write.sorted {
-                            return;
-                        }
-                    }
+                    ctx.mark(ACTIVE, i);
+                    ctx.write(i, *element)?;
 
                     i += 1;
-                    thread::sleep(*sleep_time);
                 }
             }
         }
+        ctx.clear(highlight::BUCKET);
     }
+    Ok(())
 }
 
 pub mod quick_sorting {
-    use std::time::Duration;
-    use std::thread;
-    use std::sync::{Arc, RwLock};
     use super::*;
+    use super::super::commands::{Partitioning, PivotSelection};
 
     const MAX_RUN_SIZE: usize = 16;     // Used in quicktimsort. If the array given is less than MAX_RUN_SIZE in length, then sort with insertion sort
+    const NINTHER_MIN_SIZE: usize = 9;  // Below this the ninther is just a median of three
+
+    // Index of whichever of the three elements has the middle value.
+    fn median_of_three(ctx: &SortContext, mut a: usize, mut b: usize, c: usize) -> SortResult<usize> {
+        if ctx.compare(a, b)? == Ordering::Greater {
+            std::mem::swap(&mut a, &mut b);
+        }
+        // Now a <= b
+        if ctx.compare(b, c)? != Ordering::Greater {
+            Ok(b)
+        } else if ctx.compare(a, c)? == Ordering::Greater {
+            Ok(a)
+        } else {
+            Ok(c)
+        }
+    }
+
+    // Index of the element to use as the pivot in [l, r].
+    fn select_pivot(ctx: &SortContext, l: usize, r: usize, pivot: PivotSelection) -> SortResult<usize> {
+        let mid = l + (r - l)/2;
+
+        match pivot {
+            PivotSelection::Last => Ok(r),
+            PivotSelection::First => Ok(l),
+            PivotSelection::Middle => Ok(mid),
+            PivotSelection::Random => Ok(ctx.random_range(l, r + 1)),
+            PivotSelection::MedianOfThree => median_of_three(ctx, l, mid, r),
+            PivotSelection::Ninther => {
+                if r - l + 1 < NINTHER_MIN_SIZE {
+                    median_of_three(ctx, l, mid, r)
+                } else {
+                    let step = (r - l)/8;
+                    let first = median_of_three(ctx, l, l + step, l + 2*step)?;
+                    let middle = median_of_three(ctx, mid - step, mid, mid + step)?;
+                    let last = median_of_three(ctx, r - 2*step, r - step, r)?;
+                    median_of_three(ctx, first, middle, last)
+                }
+            },
+        }
+    }
 
     // Lomuto partition scheme: https://en.wikipedia.org/wiki/Quicksort#Lomuto_partition_scheme
+    // The pivot is moved to the end first. Returns the final position of the pivot.
     #[inline]
-    fn lomuto_partitioning(
-        data_arc: Arc<RwLock<DataArrWrapper>>,
-        sleep_time: Arc<Duration>,
-        l: usize,
-        r: usize,
-    ) -> usize {
-        let pivot = data_arc.read().unwrap()[r];
-        data_arc.write().unwrap().set_pivot(r);
+    fn lomuto_partitioning(ctx: &SortContext, l: usize, r: usize, pivot: PivotSelection) -> SortResult<usize> {
+        let pivot_index = select_pivot(ctx, l, r, pivot)?;
+        if pivot_index != r {
+            ctx.swap(pivot_index, r)?;
+        }
+
+        let pivot = ctx.read(r)?;
+        ctx.mark(PIVOT, r);
 
         let mut i = l;
         for j in l..r {
-            check_for_stop_break!(data_arc);
-            {
-                // Update active info
-                let mut write = data_arc.write().unwrap();
-                write.set_active(i);
-                write.set_active_2(j);
-            }
+            // Update active info
+            ctx.mark(ACTIVE, i);
+            ctx.mark(ACTIVE_2, j);
 
-            if data_arc.read().unwrap()[j] < pivot {
-                data_arc.write().unwrap().swap(i, j);
+            if ctx.compare_value(j, pivot)? == Ordering::Less {
+                ctx.swap(i, j)?;
                 i += 1;
-                thread::sleep(*sleep_time);
             }
         }
 
-        data_arc.write().unwrap().swap(i, r);
-        i
+        ctx.swap(i, r)?;
+        Ok(i)
     }
 
-    pub fn quick_sort_lomuto(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: Arc<Duration>, l: usize, r: usize) {
-        if l < r {
-            // Not equal
-            let p = lomuto_partitioning(data_arc.clone(), sleep_time.clone(), l, r);
-            if p > 0 {
-                quick_sort_lomuto(data_arc.clone(), sleep_time.clone(), l, p - 1);
+    // Hoare partition scheme: https://en.wikipedia.org/wiki/Quicksort#Hoare_partition_scheme
+    // The pivot is moved to the start first, so that the scans always stop inside the partition.
+    // Returns j, where everything in [l, j] is <= everything in [j + 1, r].
+    #[inline]
+    fn hoare_partitioning(ctx: &SortContext, l: usize, r: usize, pivot: PivotSelection) -> SortResult<usize> {
+        let mut pivot_index = select_pivot(ctx, l, r, pivot)?;
+        if pivot_index != l {
+            ctx.swap(pivot_index, l)?;
+            pivot_index = l;
+        }
+
+        let pivot = ctx.read(l)?;
+        ctx.mark(PIVOT, l);
+
+        // The two scan pointers are shown as the active elements.
+        let (mut i, mut j) = (l, r);
+        loop {
+            ctx.mark(ACTIVE, i);
+            while ctx.compare_value(i, pivot)? == Ordering::Less {
+                i += 1;
+                ctx.mark(ACTIVE, i);
             }
-            if p < r {
-                quick_sort_lomuto(data_arc.clone(), sleep_time, p + 1, r);
+
+            ctx.mark(ACTIVE_2, j);
+            while ctx.compare_value(j, pivot)? == Ordering::Greater {
+                j -= 1;
+                ctx.mark(ACTIVE_2, j);
             }
+
+            if i >= j {
+                return Ok(j);
+            }
+
+            ctx.swap(i, j)?;
+            // Keep the pivot highlighted if it was moved.
+            if pivot_index == i {
+                pivot_index = j;
+                ctx.mark(PIVOT, j);
+            } else if pivot_index == j {
+                pivot_index = i;
+                ctx.mark(PIVOT, i);
+            }
+
+            i += 1;
+            j -= 1;
         }
     }
 
-    pub fn quick_sort_lomuto_multithreaded(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: Arc<Duration>, l: usize, r: usize) {
-        if l < r {
-            let mut child_threads: Vec<thread::JoinHandle<()>> = Vec::new();
+    // Partitions [l, r]. Returns the (exclusive) end of the left part and the start of the right part that are left to sort.
+    fn partition(
+        ctx: &SortContext,
+        l: usize,
+        r: usize,
+        partitioning: Partitioning,
+        pivot: PivotSelection,
+    ) -> SortResult<(usize, usize)> {
+        match partitioning {
+            Partitioning::Lomuto => {
+                let p = lomuto_partitioning(ctx, l, r, pivot)?;
+                Ok((p, p + 1))
+            },
+            Partitioning::Hoare => {
+                let j = hoare_partitioning(ctx, l, r, pivot)?;
+                Ok((j + 1, j + 1))
+            },
+        }
+    }
 
+    pub fn quick_sort(
+        ctx: &SortContext,
+        l: usize,
+        r: usize,
+        partitioning: Partitioning,
+        pivot: PivotSelection,
+    ) -> SortResult {
+        if l < r {
             // Not equal
-            let p = lomuto_partitioning(data_arc.clone(), sleep_time.clone(), l, r);
-            if p > 0 {
-                let cln = data_arc.clone();
-                let slp_cln = sleep_time.clone();
-                child_threads.push(thread::spawn(move || {
-                    quick_sort_lomuto_multithreaded(cln, slp_cln, l, p - 1);
-                }));
+            let (left_end, right_start) = partition(ctx, l, r, partitioning, pivot)?;
+            if left_end > l {
+                quick_sort(ctx, l, left_end - 1, partitioning, pivot)?;
             }
-            if p < r {
-                let cln = data_arc.clone();
-                let slp_cln = sleep_time.clone();
-                child_threads.push(thread::spawn(move || {
-                    quick_sort_lomuto_multithreaded(cln, slp_cln, p + 1, r);
-                }));
+            if right_start < r {
+                quick_sort(ctx, right_start, r, partitioning, pivot)?;
             }
+        }
+        Ok(())
+    }
 
-            for child in child_threads {
-                child.join().unwrap();
-            }
-        } 
+    // Splits the two halves between the sort pool's workers, down to its cutoff depth. Must be run inside the pool.
+    pub fn quick_sort_multithreaded(
+        ctx: &SortContext,
+        l: usize,
+        r: usize,
+        partitioning: Partitioning,
+        pivot: PivotSelection,
+        depth: usize,
+    ) -> SortResult {
+        if l < r {
+            // Not equal
+            let (left_end, right_start) = partition(ctx, l, r, partitioning, pivot)?;
+            ctx.pool().join(
+                depth,
+                || if left_end > l {
+                    quick_sort_multithreaded(ctx, l, left_end - 1, partitioning, pivot, depth + 1)
+                } else {
+                    Ok(())
+                },
+                || if right_start < r {
+                    quick_sort_multithreaded(ctx, right_start, r, partitioning, pivot, depth + 1)
+                } else {
+                    Ok(())
+                },
+            )?;
+        }
+        Ok(())
     }
 
     // Like timsort but for quicksort instead (because why not)
     // Does regular quicksort until the array size becomes less than MAX_RUN_SIZE, where it then switches to insertion
     // sort, since insertion sort works well with small arrays.
-    pub fn quicktimsort(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: Arc<Duration>, l: usize, r: usize) {        
+    pub fn quicktimsort(
+        ctx: &SortContext,
+        l: usize,
+        r: usize,
+        partitioning: Partitioning,
+        pivot: PivotSelection,
+    ) -> SortResult {
         if l < r {
             if r - l < MAX_RUN_SIZE {
-                insertion_sort(data_arc, &sleep_time, l, r);
+                insertion_sort(ctx, l, r)?;
             } else {
-                let p = lomuto_partitioning(data_arc.clone(), sleep_time.clone(), l, r);
-                if p > 0 {
-                    quicktimsort(data_arc.clone(), sleep_time.clone(), l, p - 1);
+                let (left_end, right_start) = partition(ctx, l, r, partitioning, pivot)?;
+                if left_end > l {
+                    quicktimsort(ctx, l, left_end - 1, partitioning, pivot)?;
                 }
-                if p < r {
-                    quicktimsort(data_arc.clone(), sleep_time, p + 1, r);
+                if right_start < r {
+                    quicktimsort(ctx, right_start, r, partitioning, pivot)?;
                 }
             }
         }
+        Ok(())
     }
 
-    pub fn quicktimsort_multithreaded(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: Arc<Duration>, l: usize, r: usize) {
+    pub fn quicktimsort_multithreaded(
+        ctx: &SortContext,
+        l: usize,
+        r: usize,
+        partitioning: Partitioning,
+        pivot: PivotSelection,
+        depth: usize,
+    ) -> SortResult {
         if l < r {
             if r - l < MAX_RUN_SIZE {
-                insertion_sort(data_arc, &sleep_time, l, r);
+                insertion_sort(ctx, l, r)?;
             } else {
-                let mut child_threads: Vec<thread::JoinHandle<()>> = Vec::new();
-
-                // Not equal
-                let p = lomuto_partitioning(data_arc.clone(), sleep_time.clone(), l, r);
-                if p > 0 {
-                    let cln = data_arc.clone();
-                    let slp_cln = sleep_time.clone();
-                    child_threads.push(thread::spawn(move || {
-                        quick_sort_lomuto_multithreaded(cln, slp_cln, l, p - 1);
-                    }));
-                }
-                if p < r {
-                    let cln = data_arc.clone();
-                    let slp_cln = sleep_time.clone();
-                    child_threads.push(thread::spawn(move || {
-                        quick_sort_lomuto_multithreaded(cln, slp_cln, p + 1, r);
-                    }));
-                }
-
-                for child in child_threads {
-                    child.join().unwrap();
-                }
+                let (left_end, right_start) = partition(ctx, l, r, partitioning, pivot)?;
+                ctx.pool().join(
+                    depth,
+                    || if left_end > l {
+                        quicktimsort_multithreaded(ctx, l, left_end - 1, partitioning, pivot, depth + 1)
+                    } else {
+                        Ok(())
+                    },
+                    || if right_start < r {
+                        quicktimsort_multithreaded(ctx, right_start, r, partitioning, pivot, depth + 1)
+                    } else {
+                        Ok(())
+                    },
+                )?;
             }
         }
+        Ok(())
+    }
+}
+
+// Shifts the elements in [to, from) right by one, then writes the value (which was at `from`) into `to`.
+// Shared by the in-place merges and binary insertion sort.
+fn shift_and_insert(ctx: &SortContext, value: usize, from: usize, to: usize) -> SortResult {
+    let mut index = from;
+    while index != to {
+        ctx.mark(ACTIVE, index);
+        let shifted = ctx.read(index - 1)?;
+        ctx.write(index, shifted)?;
+        index -= 1;
     }
+    ctx.write(to, value)
 }
 
 // Works kind of like pushing the left array into the right array.
-// Outside of "merge_sorting" sub module due to it's use in TimSort.
-fn merge_in_place(
-    data_arc: Arc<RwLock<DataArrWrapper>>,
-    sleep_time: Arc<Duration>,
-    mut start: usize,
-    mut mid: usize,
-    end: usize,
-) {
+// Outside of "merge_sorting" sub module since TimSort's merge is built on the same shifting.
+fn merge_in_place(ctx: &SortContext, mut start: usize, mut mid: usize, end: usize) -> SortResult {
     let mut start2 = mid + 1;
 
-    let mid_less_than_start2 = {
-        let read = data_arc.read().unwrap();
-        read[mid] <= read[start2]
-    };
-    if mid_less_than_start2 {
-        return; // Exit
+    if ctx.compare(mid, start2)? != Ordering::Greater {
+        return Ok(()); // Exit
     }
 
     while start <= mid && start2 <= end {
-        check_for_stop!(data_arc);
-
-        let start_less_than_start2 = {
-            let read = data_arc.read().unwrap();
-            read[start] <= read[start2]
-        };
-        if start_less_than_start2 { // Then it is in the correct place.
+        if ctx.compare(start, start2)? != Ordering::Greater { // Then it is in the correct place.
             start += 1;
         } else {
             // if element 1 is not in the right place, move it until it is.
-            let value = data_arc.read().unwrap()[start2]; // Element 2
-            let mut index = start2;
-
-            {
-                // Shift all elements between element 1 and element 2 right by 1 to insert this element.
-                let mut write = data_arc.write().unwrap();
-                write.set_pivot(start2);
-
-                while index != start {
-                    write.set_active(index);
-                    write[index] = write[index - 1];
-                    index -= 1;
-                }
-                write[start] = value;
-            }
+            let value = ctx.read(start2)?; // Element 2
+
+            // Shift all elements between element 1 and element 2 right by 1 to insert this element.
+            ctx.mark(PIVOT, start2);
+            shift_and_insert(ctx, value, start2, start)?;
+
             start += 1;
             mid += 1;
             start2 += 1;
-
-            thread::sleep(*sleep_time);
         }
     }
+    Ok(())
 }
 
 pub mod merge_sorting {
-    use std::time::Duration;
-    use std::thread;
-    use std::sync::{Arc, RwLock};
     use super::*;
 
-    pub fn merge_sort_in_place(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: Arc<Duration>, l: usize, r: usize) {
+    pub fn merge_sort_in_place(ctx: &SortContext, l: usize, r: usize) -> SortResult {
         if l < r {
-            check_for_stop!(data_arc);
-
             let m = (l + r) / 2;
 
-            merge_sort_in_place(data_arc.clone(), sleep_time.clone(), l, m);
-            merge_sort_in_place(data_arc.clone(), sleep_time.clone(), m + 1, r);
+            merge_sort_in_place(ctx, l, m)?;
+            merge_sort_in_place(ctx, m + 1, r)?;
 
-            merge_in_place(data_arc, sleep_time, l, m, r);
+            ctx.mark_range(highlight::MERGE, l, r - l + 1);
+            merge_in_place(ctx, l, m, r)?;
+            ctx.unmark_range(highlight::MERGE, l, r - l + 1);
         }
+        Ok(())
     }
 
-    pub fn merge_sort_in_place_multithreaded(data_arc: Arc<RwLock<DataArrWrapper>>, sleep_time: Arc<Duration>, l: usize, r: usize) {
+    // Splits the two halves between the sort pool's workers, down to its cutoff depth. Must be run inside the pool.
+    pub fn merge_sort_in_place_multithreaded(ctx: &SortContext, l: usize, r: usize, depth: usize) -> SortResult {
         if l < r {
-            check_for_stop!(data_arc);
-            let mut child_threads: Vec<thread::JoinHandle<()>> = Vec::new();
-
             let m = (l + r) / 2;
 
-            let cln = data_arc.clone();
-            let slp_cln = sleep_time.clone();
-            child_threads.push(thread::spawn(move || {
-                merge_sort_in_place_multithreaded(cln, slp_cln, l, m)
-            }));
+            ctx.pool().join(
+                depth,
+                || merge_sort_in_place_multithreaded(ctx, l, m, depth + 1),
+                || merge_sort_in_place_multithreaded(ctx, m + 1, r, depth + 1),
+            )?;
+
+            ctx.mark_range(highlight::MERGE, l, r - l + 1);
+            merge_in_place(ctx, l, m, r)?;
+            ctx.unmark_range(highlight::MERGE, l, r - l + 1);
+        }
+        Ok(())
+    }
+}
+
+// TimSort: https://en.wikipedia.org/wiki/Timsort, following the description in CPython's listsort.txt.
+// Runs are merged in place (so that every element stays visible), with galloping to move whole blocks at once.
+pub mod tim_sorting {
+    use super::*;
+
+    const MIN_MERGE: usize = 32;    // Arrays shorter than this are just sorted with binary insertion sort
+    const MIN_GALLOP: usize = 7;    // Starting number of wins in a row before a merge starts galloping
+
+    // Minimum run length, chosen so that len / minrun is a power of 2 (or just under), keeping merges balanced.
+    fn min_run_length(mut len: usize) -> usize {
+        let mut remainder = 0;  // Becomes 1 if any bits are shifted off
+        while len >= MIN_MERGE {
+            remainder |= len & 1;
+            len >>= 1;
+        }
+        len + remainder
+    }
+
+    // Reverses [lo, hi)
+    fn reverse(ctx: &SortContext, mut lo: usize, mut hi: usize) -> SortResult {
+        while lo + 1 < hi {
+            ctx.swap(lo, hi - 1)?;
+            lo += 1;
+            hi -= 1;
+        }
+        Ok(())
+    }
+
+    // Moves [middle, hi) in front of [lo, middle), keeping the order of each block.
+    fn rotate(ctx: &SortContext, lo: usize, middle: usize, hi: usize) -> SortResult {
+        reverse(ctx, lo, middle)?;
+        reverse(ctx, middle, hi)?;
+        reverse(ctx, lo, hi)
+    }
+
+    // Length of the run starting at lo, ending before hi. Strictly descending runs are reversed, so all runs end up
+    // ascending. (Strictly, so that reversing keeps the sort stable.)
+    fn count_run_and_make_ascending(ctx: &SortContext, lo: usize, hi: usize) -> SortResult<usize> {
+        let mut run_hi = lo + 1;
+        if run_hi == hi {
+            return Ok(1);
+        }
+
+        if ctx.compare(run_hi, lo)? == Ordering::Less {
+            run_hi += 1;
+            while run_hi < hi && ctx.compare(run_hi, run_hi - 1)? == Ordering::Less {
+                run_hi += 1;
+            }
+            reverse(ctx, lo, run_hi)?;
+        } else {
+            run_hi += 1;
+            while run_hi < hi && ctx.compare(run_hi, run_hi - 1)? != Ordering::Less {
+                run_hi += 1;
+            }
+        }
+        Ok(run_hi - lo)
+    }
+
+    // Sorts [lo, hi), where [lo, start) is already sorted. Finds where each element goes with a binary search.
+    fn binary_insertion_sort(ctx: &SortContext, lo: usize, hi: usize, start: usize) -> SortResult {
+        for i in start..hi {
+            ctx.mark(PIVOT, i);
+            let value = ctx.read(i)?;
+
+            // Find the first element greater than the value, so equal elements stay in order.
+            let (mut left, mut right) = (lo, i);
+            while left < right {
+                let middle = left + (right - left)/2;
+                ctx.mark(ACTIVE_2, middle);
+                if ctx.compare_value(middle, value)? == Ordering::Greater {
+                    right = middle;
+                } else {
+                    left = middle + 1;
+                }
+            }
+
+            if left != i {
+                shift_and_insert(ctx, value, i, left)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Finds the first index in [lo, hi) that the value should go before, searching 1, 2, 4, 8... elements from lo and
+    // then doing a binary search between the last two probes. If `after_equal`, equal elements go before the value.
+    fn gallop(ctx: &SortContext, value: usize, lo: usize, hi: usize, after_equal: bool) -> SortResult<usize> {
+        let goes_before = |index: usize| -> SortResult<bool> {
+            ctx.mark(ACTIVE_2, index);
+            Ok(match ctx.compare_value(index, value)? {
+                Ordering::Less => true,
+                Ordering::Equal => after_equal,
+                Ordering::Greater => false,
+            })
+        };
+
+        let (mut left, mut right) = (lo, hi);
+        let mut offset = 1;
+        while lo + offset - 1 < hi {
+            let probe = lo + offset - 1;
+            if goes_before(probe)? {
+                left = probe + 1;
+                offset *= 2;
+            } else {
+                right = probe;
+                break;
+            }
+        }
+
+        while left < right {
+            let middle = left + (right - left)/2;
+            if goes_before(middle)? {
+                left = middle + 1;
+            } else {
+                right = middle;
+            }
+        }
+        Ok(left)
+    }
+
+    // Merges the sorted runs [start, mid] and [mid + 1, end] in place. Works like merge_in_place, but once one run has
+    // won min_gallop times in a row it gallops instead, moving whole blocks at once until the blocks get short again.
+    fn merge(ctx: &SortContext, mut start: usize, mut mid: usize, mut end: usize, min_gallop: &mut usize) -> SortResult {
+        // Elements at the start of the left run that are <= the first of the right run are already in place,
+        // as are elements at the end of the right run that are >= the last of the left run.
+        let first_right = ctx.read(mid + 1)?;
+        start = gallop(ctx, first_right, start, mid + 1, true)?;
+        if start > mid {
+            return Ok(());
+        }
+        let last_left = ctx.read(mid)?;
+        end = gallop(ctx, last_left, mid + 1, end + 1, false)? - 1;
+
+        let mut start2 = mid + 1;
+        let mut left_wins = 0;  // Times in a row that each run has had the smaller element
+        let mut right_wins = 0;
+
+        while start <= mid && start2 <= end {
+            if left_wins >= *min_gallop || right_wins >= *min_gallop {
+                // Left elements <= the first right element are already in place.
+                let value = ctx.read(start2)?;
+                let left_block = gallop(ctx, value, start, mid + 1, true)? - start;
+                start += left_block;
+                if start > mid {
+                    break;
+                }
+
+                // Right elements < the first left element all move in front of it.
+                let value = ctx.read(start)?;
+                let right_block = gallop(ctx, value, start2, end + 1, false)? - start2;
+                ctx.mark(PIVOT, start);
+                rotate(ctx, start, start2, start2 + right_block)?;
+                start += right_block;
+                mid += right_block;
+                start2 += right_block;
+
+                if left_block < MIN_GALLOP && right_block < MIN_GALLOP {
+                    // Galloping isn't paying off, so make it harder to start again.
+                    *min_gallop += 1;
+                    left_wins = 0;
+                    right_wins = 0;
+                } else if *min_gallop > 1 {
+                    *min_gallop -= 1;
+                }
+            } else if ctx.compare(start, start2)? != Ordering::Greater {
+                start += 1;
+                left_wins += 1;
+                right_wins = 0;
+            } else {
+                let value = ctx.read(start2)?;
+                ctx.mark(PIVOT, start2);
+                shift_and_insert(ctx, value, start2, start)?;
+
+                start += 1;
+                mid += 1;
+                start2 += 1;
+                right_wins += 1;
+                left_wins = 0;
+            }
+        }
+        Ok(())
+    }
+
+    // Merges the runs at i and i + 1 on the stack.
+    fn merge_at(ctx: &SortContext, runs: &mut Vec<(usize, usize)>, i: usize, min_gallop: &mut usize) -> SortResult {
+        let (start, len) = runs[i];
+        let (start2, len2) = runs[i + 1];
 
-            let cln = data_arc.clone();
-            let slp_cln = sleep_time.clone();
-            child_threads.push(thread::spawn(move || {
-                merge_sort_in_place_multithreaded(cln, slp_cln, m + 1, r)
-            }));
+        merge(ctx, start, start + len - 1, start2 + len2 - 1, min_gallop)?;
 
-            for child in child_threads {
-                child.join().unwrap();
+        ctx.unmark_range(highlight::RUN, start, len);
+        ctx.unmark_range(highlight::RUN, start2, len2);
+        ctx.mark_range(highlight::RUN, start, len + len2);
+        runs[i].1 += len2;
+        runs.remove(i + 1);
+        Ok(())
+    }
+
+    // Merges runs until the lengths on the stack satisfy the invariants (for consecutive runs X, Y, Z, with Z on top):
+    // |X| > |Y| + |Z| and |Y| > |Z|. Keeps the merges balanced and the stack short.
+    fn merge_collapse(ctx: &SortContext, runs: &mut Vec<(usize, usize)>, min_gallop: &mut usize) -> SortResult {
+        while runs.len() > 1 {
+            let mut n = runs.len() - 2;
+            let len = |i: usize| runs[i].1;
+
+            if (n > 0 && len(n - 1) <= len(n) + len(n + 1)) || (n > 1 && len(n - 2) <= len(n - 1) + len(n)) {
+                if len(n - 1) < len(n + 1) {
+                    n -= 1;
+                }
+                merge_at(ctx, runs, n, min_gallop)?;
+            } else if len(n) <= len(n + 1) {
+                merge_at(ctx, runs, n, min_gallop)?;
+            } else {
+                break;
             }
+        }
+        Ok(())
+    }
+
+    // Merges everything left on the stack once the whole array has been split into runs.
+    fn merge_force_collapse(ctx: &SortContext, runs: &mut Vec<(usize, usize)>, min_gallop: &mut usize) -> SortResult {
+        while runs.len() > 1 {
+            let mut n = runs.len() - 2;
+            if n > 0 && runs[n - 1].1 < runs[n + 1].1 {
+                n -= 1;
+            }
+            merge_at(ctx, runs, n, min_gallop)?;
+        }
+        Ok(())
+    }
 
-            merge_in_place(data_arc, sleep_time, l, m, r);
+    pub fn tim_sort(ctx: &SortContext) -> SortResult {
+        let len = ctx.len();
+        if len < 2 {
+            return Ok(());
         }
+
+        if len < MIN_MERGE {
+            let run_len = count_run_and_make_ascending(ctx, 0, len)?;
+            return binary_insertion_sort(ctx, 0, len, run_len);
+        }
+
+        let min_run = min_run_length(len);
+        let mut min_gallop = MIN_GALLOP;
+        let mut runs: Vec<(usize, usize)> = Vec::new();     // (start, length) of the runs waiting to be merged
+
+        let mut lo = 0;
+        while lo < len {
+            let mut run_len = count_run_and_make_ascending(ctx, lo, len)?;
+
+            // Extend short runs to min_run
+            if run_len < min_run {
+                let forced_len = min_run.min(len - lo);
+                binary_insertion_sort(ctx, lo, lo + forced_len, lo + run_len)?;
+                run_len = forced_len;
+            }
+
+            runs.push((lo, run_len));
+            ctx.mark_range(highlight::RUN, lo, run_len);
+            merge_collapse(ctx, &mut runs, &mut min_gallop)?;
+
+            lo += run_len;
+        }
+
+        merge_force_collapse(ctx, &mut runs, &mut min_gallop)?;
+        ctx.unmark_range(highlight::RUN, 0, len);
+        Ok(())
     }
 }
@@ -0,0 +1,410 @@
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use yaml_rust::{Yaml, YamlLoader};
+
+use super::{
+    DataArrWrapper, ShuffleType, SortArray, SortInstruction,
+    algorithms::{self, Complexity, RadixSort, SortAlgorithm, SortRegistry},
+    context::{SortContext, SortCounters},
+    distribution::{self, Distribution, ALL_DISTRIBUTIONS},
+    random::SortRng,
+    shuffles,
+    trace::{Operation, TracePlayer},
+};
+
+// Everything the sort registry reads from the config, with no sleeping.
+const TEST_CONFIG: &str = "
+quicksort_partitioning: lomuto
+merge_sort_type: in_place
+heap_sort_type: binary
+radix_lsd_base: 10
+sort_workers: 4
+parallel_cutoff_depth: 4
+bubble_sleep: 0
+cocktail_shaker_sleep: 0
+insertion_sleep: 0
+selection_sleep: 0
+shell_sleep: 0
+quick_sleep: 0
+merge_sleep: 0
+tim_sleep: 0
+heap_sleep: 0
+radix_sleep: 0
+";
+
+const STABLE_SORTS: [&str; 5] = ["bubble", "cocktail_shaker", "insertion", "merge", "tim"];     // Ids of sorts that should be stable
+const RADIX_BASES: [usize; 5] = [2, 3, 10, 16, 36];
+const SHUFFLE_TYPES: [ShuffleType; 5] = [
+    ShuffleType::FisherYates,
+    ShuffleType::Sattolo,
+    ShuffleType::Local { radius: 8 },
+    ShuffleType::RandomPairs { pairs: 20 },
+    ShuffleType::ReverseRuns { runs: 8 },
+];
+pub(crate) const TEST_LEN: usize = 200;
+pub(crate) const TEST_SEED: u64 = 0x5EED;  // Every random number in the tests comes from this, so failures can be repeated
+const TIMEOUT: Duration = Duration::from_secs(10);     // For sorts running on their own thread
+
+pub(crate) fn registry() -> SortRegistry {
+    let conf: Yaml = YamlLoader::load_from_str(TEST_CONFIG).unwrap().remove(0);
+    let registry = SortRegistry::from(&conf);
+    registry.rng.reseed(TEST_SEED);
+    registry
+}
+
+// Calls the test with every sort, with every combination of its options, and radix sort at several bases.
+fn for_each_sort(mut test: impl FnMut(&dyn SortAlgorithm, &SortRegistry)) {
+    let registry = registry();
+    for sort in registry.iter() {
+        let variants = sort.algorithm.variants();
+        if variants.is_empty() {
+            test(sort.algorithm.as_ref(), &registry);
+        } else {
+            for variant in variants.iter() {
+                test(variant.as_ref(), &registry);
+            }
+        }
+    }
+
+    for &base in RADIX_BASES.iter() {
+        test(&RadixSort { base }, &registry);
+    }
+}
+
+fn shuffled(mut values: Vec<usize>, rng: &SortRng) -> Vec<usize> {
+    distribution::shuffle(&mut values, rng);
+    values
+}
+
+fn inputs() -> Vec<(&'static str, Vec<usize>)> {
+    let rng = SortRng::new(TEST_SEED);
+    vec![
+        ("empty", vec![]),
+        ("single element", vec![0]),
+        ("two sorted", vec![0, 1]),
+        ("two reversed", vec![1, 0]),
+        ("two equal", vec![1, 1]),
+        ("random", shuffled((0..TEST_LEN).collect(), &rng)),
+        ("sorted", (0..TEST_LEN).collect()),
+        ("reversed", (0..TEST_LEN).rev().collect()),
+        ("duplicates", shuffled((0..TEST_LEN).map(|i| i % 7).collect(), &rng)),
+        ("all equal", vec![5; TEST_LEN]),
+    ]
+}
+
+fn context(data: &Arc<RwLock<DataArrWrapper>>, sorts: &SortRegistry) -> SortContext {
+    SortContext::new(
+        Arc::clone(data),
+        Arc::new(SortCounters::default()),
+        Duration::default(),
+        Arc::clone(&sorts.pool),
+        Arc::clone(&sorts.rng),
+    )
+}
+
+// Where each of a sort's operations touched the array, leaving out the values and highlights. Sorted, since
+// multithreaded sorts interleave the operations of their threads differently each run.
+fn array_accesses(trace: &[Operation]) -> Vec<(char, usize, usize)> {
+    let mut accesses: Vec<(char, usize, usize)> = trace.iter()
+        .filter_map(|operation| match *operation {
+            Operation::Compare(a, b) => Some(('c', a, b)),
+            Operation::CompareValue(index, _) => Some(('v', index, 0)),
+            Operation::Swap(a, b) => Some(('s', a, b)),
+            Operation::Write { index, .. } => Some(('w', index, 0)),
+            _ => None,
+        })
+        .collect();
+    accesses.sort_unstable();
+    accesses
+}
+
+fn run_shuffle(shuffle_type: ShuffleType, len: usize, sorts: &SortRegistry) -> Vec<usize> {
+    let mut wrapper = DataArrWrapper::new((0..len).collect(), len);
+    wrapper.sorted = false;
+
+    let data = Arc::new(RwLock::new(wrapper));
+    let ctx = context(&data, sorts);
+    shuffles::shuffle(&ctx, shuffle_type).unwrap();
+
+    let values = data.read().unwrap().arr.clone();
+    values
+}
+
+// Returns false if the array's sorting thread did not finish in time (or panicked).
+fn wait_until_stopped(array: &SortArray) -> bool {
+    let start = Instant::now();
+    while !array.data.read().unwrap().sorted {
+        if start.elapsed() > TIMEOUT {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    true
+}
+
+#[test]
+fn sorts_produce_sorted_permutation() {
+    for_each_sort(|algorithm, sorts| {
+        for (input_name, input) in inputs() {
+            let mut expected = input.clone();
+            expected.sort_unstable();
+
+            let (output, _) = algorithms::run_headless(algorithm, input, sorts);
+            // Equal to the sorted input, so both sorted and a permutation of the input.
+            assert_eq!(output, expected, "{} on {} input (seed {})", algorithm.full_name(), input_name, TEST_SEED);
+        }
+    });
+}
+
+#[test]
+fn stable_sorts_are_stable() {
+    // Few unique keys, and the same keys tagged with their original position, which no two elements share.
+    let rng = SortRng::new(TEST_SEED);
+    let keys: Vec<usize> = (0..TEST_LEN).map(|_| rng.range(0, 10)).collect();
+    let tagged: Vec<usize> = keys.iter()
+        .enumerate()
+        .map(|(position, key)| key * TEST_LEN + position)
+        .collect();
+
+    // A stable sort treats equal keys as if they were ordered by position, so it goes through the array the same way
+    // for the keys alone as it does for the tagged keys.
+    for_each_sort(|algorithm, sorts| {
+        if !STABLE_SORTS.contains(&algorithm.id()) {
+            return;
+        }
+
+        let by_key = algorithms::run_headless_traced(algorithm, keys.clone(), sorts);
+        let by_tag = algorithms::run_headless_traced(algorithm, tagged.clone(), sorts);
+        assert!(
+            array_accesses(by_key.trace.as_ref().unwrap()) == array_accesses(by_tag.trace.as_ref().unwrap()),
+            "{} is not stable (seed {})", algorithm.full_name(), TEST_SEED,
+        );
+    });
+
+    // Radix LSD sort does not compare, so it goes through the array the same way whatever the order. It sorts by the
+    // lowest digit first though, so with the key in the lowest digits and the position above them, the passes over the
+    // key's digits are the passes it would make over the keys alone. After those, equal keys should be in order of
+    // position.
+    let sorts = registry();
+    let max_key = *keys.iter().max().unwrap();
+    for &base in RADIX_BASES.iter() {
+        let mut key_digits = 1;
+        while base.pow(key_digits) <= max_key {
+            key_digits += 1;
+        }
+        let position_unit = base.pow(key_digits);
+        let positioned: Vec<usize> = keys.iter()
+            .enumerate()
+            .map(|(position, key)| position * position_unit + key)
+            .collect();
+
+        // Every pass writes every element once.
+        let mut after_key_passes = positioned.clone();
+        let sorted = algorithms::run_headless_traced(&RadixSort { base }, positioned, &sorts);
+        for operation in sorted.trace.unwrap().iter()
+            .filter(|operation| matches!(operation, Operation::Write { .. }))
+            .take(key_digits as usize * TEST_LEN)
+        {
+            if let Operation::Write { index, new, .. } = *operation {
+                after_key_passes[index] = new;
+            }
+        }
+
+        let mut expected = after_key_passes.clone();
+        expected.sort_unstable_by_key(|&value| (value % position_unit, value / position_unit));
+        assert_eq!(after_key_passes, expected, "Radix LSD Sort (base {}) is not stable (seed {})", base, TEST_SEED);
+    }
+}
+
+#[test]
+fn instructions_on_tiny_arrays() {
+    let sorts = Arc::new(registry());
+
+    for len in 0..3 {
+        for &shuffle_type in SHUFFLE_TYPES.iter() {
+            let mut array = SortArray::new(len, Arc::clone(&sorts), Distribution::Sorted, shuffle_type, Duration::default(), 0);
+            array.instruction(SortInstruction::Shuffle);
+            assert!(wait_until_stopped(&array), "{:?} shuffle did not finish on array of length {}", shuffle_type, len);
+        }
+
+        let mut array = SortArray::new(len, Arc::clone(&sorts), Distribution::Sorted, ShuffleType::FisherYates, Duration::default(), 0);
+        for id in 0..sorts.iter().count() {
+            array.instruction(SortInstruction::Shuffle);
+            assert!(wait_until_stopped(&array));
+
+            array.instruction(SortInstruction::Sort(id));
+            assert!(wait_until_stopped(&array), "{} did not finish on array of length {}", sorts[id].algorithm.name(), len);
+            assert_eq!(array.data.read().unwrap().arr, (0..len).collect::<Vec<usize>>());
+        }
+
+        array.instruction(SortInstruction::Reverse);
+        array.instruction(SortInstruction::Reset);
+        assert_eq!(array.data.read().unwrap().arr, (0..len).collect::<Vec<usize>>());
+    }
+}
+
+#[test]
+fn distributions_generate_values_up_to_length() {
+    let rng = SortRng::new(TEST_SEED);
+    for distribution in ALL_DISTRIBUTIONS.iter() {
+        for &len in [0, 1, 2, 3, TEST_LEN].iter() {
+            let values = distribution.generate(len, &rng);
+            assert_eq!(values.len(), len, "{} generated the wrong number of values", distribution.name());
+            assert!(distribution::max_value(&values) <= len, "{} generated values larger than the length", distribution.name());
+        }
+    }
+}
+
+#[test]
+fn shuffles_produce_permutations() {
+    let sorts = registry();
+
+    for &shuffle_type in SHUFFLE_TYPES.iter() {
+        for &len in [0, 1, 2, 3, TEST_LEN].iter() {
+            let mut output = run_shuffle(shuffle_type, len, &sorts);
+            output.sort_unstable();
+            assert_eq!(output, (0..len).collect::<Vec<usize>>(), "{:?} shuffle on length {} (seed {})", shuffle_type, len, TEST_SEED);
+        }
+    }
+}
+
+#[test]
+fn sattolo_makes_a_single_cycle() {
+    let output = run_shuffle(ShuffleType::Sattolo, TEST_LEN, &registry());
+
+    // Following where each element went must visit every index before getting back to the start.
+    let mut index = output[0];
+    let mut steps = 1;
+    while index != 0 {
+        index = output[index];
+        steps += 1;
+    }
+    assert_eq!(steps, TEST_LEN, "seed {}", TEST_SEED);
+}
+
+#[test]
+fn same_seed_gives_same_shuffle_and_distribution() {
+    let sorts = registry();
+
+    let mut runs = Vec::new();
+    for _ in 0..2 {
+        sorts.rng.reseed(42);
+        runs.push((
+            Distribution::Gaussian.generate(TEST_LEN, &sorts.rng),
+            run_shuffle(ShuffleType::FisherYates, TEST_LEN, &sorts),
+        ));
+    }
+    assert_eq!(runs[0], runs[1]);
+}
+
+#[test]
+fn loaded_arrays_reset_to_the_loaded_values() {
+    let values = vec![9, 0, 7, 2, 2, 5];
+    let mut array = SortArray::new(TEST_LEN, Arc::new(registry()), Distribution::Random, ShuffleType::FisherYates, Duration::default(), 0);
+    array.load(String::from("test"), values.clone());
+    assert_eq!(array.data.read().unwrap().max_val, 10);
+
+    array.instruction(SortInstruction::Shuffle);
+    assert!(wait_until_stopped(&array));
+    array.instruction(SortInstruction::Reset);
+    assert_eq!(array.values(), values);
+}
+#[test]
+fn arrays_are_only_finished_once_a_sort_leaves_them_in_order() {
+    let sorts = Arc::new(registry());
+    let mut input: Vec<usize> = (0..TEST_LEN).collect();
+    distribution::shuffle(&mut input, &sorts.rng);
+
+    let mut array = SortArray::new(0, Arc::clone(&sorts), Distribution::Sorted, ShuffleType::FisherYates, Duration::default(), 0);
+    array.set_values(input);
+    assert!(!array.is_finished());
+
+    array.instruction(SortInstruction::Sort(0));
+    assert!(wait_until_stopped(&array));
+    assert!(array.is_finished());
+
+    array.instruction(SortInstruction::Reverse);
+    assert!(!array.is_finished());
+}
+
+#[test]
+fn traces_are_only_recorded_up_to_the_limit() {
+    let sorts = Arc::new(registry());
+    let mut input: Vec<usize> = (0..TEST_LEN).collect();
+    distribution::shuffle(&mut input, &sorts.rng);
+
+    // Bubble sort on this many elements does far more than one operation per element.
+    for &(trace_limit, kept) in &[(0, false), (TEST_LEN, false), (usize::MAX, true)] {
+        let mut array = SortArray::new(0, Arc::clone(&sorts), Distribution::Sorted, ShuffleType::FisherYates, Duration::default(), trace_limit);
+        array.set_values(input.clone());
+        array.instruction(SortInstruction::Sort(0));
+        assert!(wait_until_stopped(&array));
+        assert_eq!(array.take_trace().is_some(), kept, "trace_limit {}", trace_limit);
+    }
+}
+
+#[test]
+fn replayed_traces_end_with_the_highlights_and_heap_cleared_like_the_array() {
+    let sorts = Arc::new(registry());
+    let heap = sorts.iter().position(|sort| sort.algorithm.id() == "heap").unwrap();
+    let mut array = SortArray::new(TEST_LEN, Arc::clone(&sorts), Distribution::Random, ShuffleType::FisherYates, Duration::default(), usize::MAX);
+    array.instruction(SortInstruction::Sort(heap));
+    assert!(wait_until_stopped(&array));
+
+    let mut player = TracePlayer::new(array.take_trace().unwrap());
+    let mut data = array.data.write().unwrap();
+    player.seek(0, &mut data);
+    while player.step_forward(&mut data) {}
+    assert!(data.highlights.sets().is_empty(), "highlights left: {:?}", data.highlights.sets());
+    assert_eq!(data.heap, None);
+}
+
+#[test]
+fn sleep_divisors_are_never_zero_and_saturate_for_long_arrays() {
+    for complexity in &[Complexity::NSquared, Complexity::NPow3Over2, Complexity::NLogN, Complexity::NW] {
+        assert_eq!(complexity.divisor(0), 1);
+    }
+    assert_eq!(Complexity::NSquared.divisor(300), 90_000);
+    // 65536 squared is 2^32, which would be 0 as a u32.
+    assert_eq!(Complexity::NSquared.divisor(65_536), u32::MAX);
+    assert_eq!(Complexity::NPow3Over2.divisor(10_000_000), u32::MAX);
+}
+
+#[test]
+fn replacing_the_values_of_a_sorting_array_stops_the_sort_first() {
+    let sorts = Arc::new(registry());
+    let mut array = SortArray::new(TEST_LEN, Arc::clone(&sorts), Distribution::Random, ShuffleType::FisherYates, Duration::default(), 0);
+
+    // Held at its first checkpoint. A new sort unpauses the array, so the old one would carry on with indices into
+    // the old array if it were still running.
+    array.instruction(SortInstruction::Pause);
+    array.instruction(SortInstruction::Sort(0));
+    thread::sleep(Duration::from_millis(20));
+
+    array.set_values(vec![3, 2, 1]);
+    array.instruction(SortInstruction::Sort(0));
+    assert!(wait_until_stopped(&array));
+    assert_eq!(array.values(), vec![1, 2, 3]);
+}
+
+#[test]
+fn a_paused_multithreaded_sort_does_not_hold_up_other_arrays() {
+    let conf: Yaml = YamlLoader::load_from_str(&TEST_CONFIG.replace("in_place", "in_place_multi")).unwrap().remove(0);
+    let sorts = Arc::new(SortRegistry::from(&conf));
+    let merge = sorts.iter().position(|sort| sort.algorithm.id() == "merge").unwrap();
+    let new_array = || SortArray::new(TEST_LEN, Arc::clone(&sorts), Distribution::Random, ShuffleType::FisherYates, Duration::default(), 0);
+
+    let mut paused = new_array();
+    paused.instruction(SortInstruction::Pause);
+    paused.instruction(SortInstruction::Sort(merge));
+    thread::sleep(Duration::from_millis(20));
+
+    let mut running = new_array();
+    running.instruction(SortInstruction::Sort(merge));
+    assert!(wait_until_stopped(&running));
+    assert!(running.is_finished());
+    paused.instruction(SortInstruction::Stop);
+}
+
@@ -0,0 +1,273 @@
+use nannou::{
+    draw::Draw,
+    geom::point::Point2,
+    color::named::*,
+};
+
+use super::{DataArrWrapper, highlight::{Highlight, Mark}};
+
+use std::fmt;
+
+pub const TIMELINE_HEIGHT: f32 = 12.0;
+const MAX_SPEED: f32 = 65536.0;     // Operations per frame
+const MIN_SPEED: f32 = 1.0 / 64.0;
+
+// The part of the array that a heap sort is currently treating as a heap. Shown as a tree over the Bars display.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HeapShape {
+    pub len: usize,
+    pub arity: usize,   // Children per node
+}
+
+// A single step of a sort, recorded so that it can be replayed in either direction.
+#[derive(Copy, Clone, Debug)]
+pub enum Operation {
+    Compare(usize, usize),
+    CompareValue(usize, usize), // (index, value) - An element compared against a value held by the sort.
+    Swap(usize, usize),
+    Write {
+        index: usize,
+        old: usize,
+        new: usize,
+    },
+    Highlight {     // A marker moving, or a range being added or removed. See Highlights::change
+        owner: usize,   // Thread that the highlight belongs to. See pool::highlight_owner
+        highlight: Highlight,
+        old: Option<Mark>,
+        new: Option<Mark>,
+    },
+    Heap {
+        old: Option<HeapShape>,
+        new: Option<HeapShape>,
+    },
+}
+
+impl Operation {
+    fn apply(&self, data: &mut DataArrWrapper) {
+        match *self {
+            Operation::Compare(..) | Operation::CompareValue(..) => (),
+            Operation::Swap(a, b) => data.arr.swap(a, b),
+            Operation::Write { index, new, .. } => data.arr[index] = new,
+            Operation::Highlight { owner, highlight, old, new } => data.change_highlight(owner, highlight, old, new),
+            Operation::Heap { new, .. } => data.heap = new,
+        }
+    }
+
+    fn undo(&self, data: &mut DataArrWrapper) {
+        match *self {
+            Operation::Compare(..) | Operation::CompareValue(..) => (),
+            Operation::Swap(a, b) => data.arr.swap(a, b),
+            Operation::Write { index, old, .. } => data.arr[index] = old,
+            Operation::Highlight { owner, highlight, old, new } => data.change_highlight(owner, highlight, new, old),
+            Operation::Heap { old, .. } => data.heap = old,
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Operation::Compare(a, b) => write!(f, "compare [{}] with [{}]", a, b),
+            Operation::CompareValue(index, value) => write!(f, "compare [{}] with {}", index, value),
+            Operation::Swap(a, b) => write!(f, "swap [{}] and [{}]", a, b),
+            Operation::Write { index, old, new } => write!(f, "write {} over {} at [{}]", new, old, index),
+            Operation::Highlight { owner, highlight, old, new } => {
+                if owner > 0 {
+                    write!(f, "worker {} ", owner - 1)?;
+                }
+                match (old, new) {
+                    (_, Some(mark)) => write!(f, "highlight {} at {}", highlight.name, mark),
+                    (Some(mark), None) => write!(f, "clear {} highlight at {}", highlight.name, mark),
+                    (None, None) => write!(f, "clear {} highlight", highlight.name),
+                }
+            },
+            Operation::Heap { new: Some(heap), .. } => write!(f, "heap of {} elements", heap.len),
+            Operation::Heap { new: None, .. } => write!(f, "clear heap"),
+        }
+    }
+}
+
+// Plays a recorded trace back into an array. The array must be in the state it was left in at the end of the trace
+// when the player is created, so playback starts at the end and can be rewound from there.
+pub struct TracePlayer {
+    ops: Vec<Operation>,
+    position: usize,    // Number of operations currently applied
+    speed: f32,         // Operations per frame. Negative plays backwards.
+    accumulated: f32,   // Fractional operations carried over between frames
+    pub playing: bool,
+    pub scrubbing: bool,
+}
+
+impl TracePlayer {
+    pub fn new(ops: Vec<Operation>) -> Self {
+        Self {
+            position: ops.len(),
+            ops,
+            speed: 1.0,
+            accumulated: 0.0,
+            playing: false,
+            scrubbing: false,
+        }
+    }
+
+    // Gives back the operations that lead up to the current position, so that the trace still matches the array.
+    pub fn into_trace(mut self) -> Vec<Operation> {
+        self.ops.truncate(self.position);
+        self.ops
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn step_forward(&mut self, data: &mut DataArrWrapper) -> bool {
+        if self.position < self.ops.len() {
+            self.ops[self.position].apply(data);
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn step_back(&mut self, data: &mut DataArrWrapper) -> bool {
+        if self.position > 0 {
+            self.position -= 1;
+            self.ops[self.position].undo(data);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn seek(&mut self, position: usize, data: &mut DataArrWrapper) {
+        let position = position.min(self.ops.len());
+        while self.position < position {
+            self.step_forward(data);
+        }
+        while self.position > position {
+            self.step_back(data);
+        }
+    }
+
+    // Seek to a ratio (0.0 -> 1.0) of the way through the trace.
+    #[inline]
+    pub fn seek_ratio(&mut self, ratio: f32, data: &mut DataArrWrapper) {
+        let position = (ratio.clamp(0.0, 1.0) * self.ops.len() as f32).round() as usize;
+        self.seek(position, data);
+    }
+
+    pub fn set_direction(&mut self, forwards: bool) {
+        self.speed = if forwards { self.speed.abs() } else { -self.speed.abs() };
+        self.accumulated = 0.0;
+        self.playing = true;
+    }
+
+    pub fn change_speed(&mut self, multiplier: f32) {
+        let new_speed = (self.speed.abs() * multiplier).clamp(MIN_SPEED, MAX_SPEED);
+        self.speed = new_speed.copysign(self.speed);
+    }
+
+    // Called every frame.
+    pub fn update(&mut self, data: &mut DataArrWrapper) {
+        if !self.playing || self.scrubbing {
+            return;
+        }
+
+        self.accumulated += self.speed;
+        while self.accumulated >= 1.0 {
+            self.accumulated -= 1.0;
+            if !self.step_forward(data) {
+                self.stop();
+                break;
+            }
+        }
+        while self.accumulated <= -1.0 {
+            self.accumulated += 1.0;
+            if !self.step_back(data) {
+                self.stop();
+                break;
+            }
+        }
+    }
+
+    #[inline]
+    fn stop(&mut self) {
+        self.playing = false;
+        self.accumulated = 0.0;
+    }
+
+    // How far through the trace playback is, from 0.0 to 1.0.
+    pub fn progress(&self) -> f32 {
+        if self.ops.is_empty() {
+            1.0
+        } else {
+            self.position as f32 / self.ops.len() as f32
+        }
+    }
+
+    pub fn label(&self) -> String {
+        let mut label = format!(
+            "Replay: {}/{} ops, {}x {}",
+            self.position,
+            self.ops.len(),
+            self.speed.abs(),
+            if !self.playing { "(paused)" } else if self.speed < 0.0 { "(backwards)" } else { "" },
+        );
+        if self.position > 0 {
+            label.push_str(&format!(" - Last: {}", self.ops[self.position - 1]));
+        }
+        label
+    }
+
+    // Draws the timeline scrubber along the bottom of the window.
+    pub fn display(&self, draw: &Draw, window_dims: (f32, f32), transform: (f32, f32)) {
+        let progress = self.progress();
+        let y = transform.1 + TIMELINE_HEIGHT / 2.0;
+
+        draw.line()
+            .start(Point2::new(transform.0, y))
+            .end(Point2::new(transform.0 + window_dims.0, y))
+            .weight(TIMELINE_HEIGHT)
+            .rgb(0.2, 0.2, 0.2);
+
+        draw.line()
+            .start(Point2::new(transform.0, y))
+            .end(Point2::new(transform.0 + window_dims.0 * progress, y))
+            .weight(TIMELINE_HEIGHT)
+            .rgb(0.9, 0.9, 0.9);
+
+        draw.text(&self.label())
+            .x_y(0.0, transform.1 + TIMELINE_HEIGHT * 2.5)
+            .w_h(window_dims.0 - TIMELINE_HEIGHT, TIMELINE_HEIGHT * 2.0)
+            .left_justify()
+            .font_size(14)
+            .color(WHITE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TracePlayer;
+    use crate::sorting_array::{
+        algorithms::{self, RadixSort},
+        distribution,
+        tests::{registry, TEST_LEN},
+    };
+
+    #[test]
+    fn traced_headless_runs_replay_from_the_input_to_sorted() {
+        let sorts = registry();
+        let mut input: Vec<usize> = (0..TEST_LEN).collect();
+        distribution::shuffle(&mut input, &sorts.rng);
+
+        let mut data = algorithms::run_headless_traced(&RadixSort { base: 4 }, input.clone(), &sorts);
+        let mut player = TracePlayer::new(data.trace.take().unwrap());
+        player.seek(0, &mut data);
+        assert_eq!(data.arr, input);
+
+        while player.step_forward(&mut data) {}
+        assert!(data.arr.iter().enumerate().all(|(i, &v)| i == v));
+    }
+}
@@ -0,0 +1,186 @@
+// SVG canvas, for vector snapshots of the arrays (e.g. for printing). Draws exactly what the window draws, as one
+// element per shape, with the origin moved to the top left and y flipped.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{
+    canvas::{Canvas, Colour, Point},
+    sorting_array::{DisplayMode, SortArray},
+    TWO_PI,
+};
+
+pub struct Svg {
+    width: f32,
+    height: f32,
+    body: String,
+}
+
+impl Svg {
+    // An empty image with a black background, like the window's.
+    pub fn new(width: f32, height: f32) -> Self {
+        let mut svg = Self {
+            width,
+            height,
+            body: String::new(),
+        };
+        svg.rect([0.0, 0.0], (width, height), Colour::BLACK);
+        svg
+    }
+
+    // The whole SVG document.
+    pub fn finish(self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n{}</svg>\n",
+            self.body,
+            w = number(self.width),
+            h = number(self.height),
+        )
+    }
+
+    pub fn save(self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.finish())
+    }
+
+    // SVG coordinates, from the top left with y going down.
+    #[inline]
+    fn point(&self, point: Point) -> (String, String) {
+        (number(point[0] + self.width / 2.0), number(self.height / 2.0 - point[1]))
+    }
+
+    fn polygon(&mut self, points: &[Point], colour: Colour) {
+        let points: Vec<String> = points.iter()
+            .map(|&point| {
+                let (x, y) = self.point(point);
+                format!("{},{}", x, y)
+            })
+            .collect();
+        let _ = writeln!(self.body, "<polygon points=\"{}\" {}/>", points.join(" "), paint("fill", colour));
+    }
+}
+
+// The arrays as they are now, drawn in the display mode at the given size the way the window draws them.
+pub fn snapshot(arrays: &[SortArray], mode: DisplayMode, dims: (f32, f32), doughnut_ratio: f32) -> Svg {
+    let mut svg = Svg::new(dims.0, dims.1);
+    for (i, arr) in arrays.iter().enumerate() {
+        arr.data.read().unwrap().draw(
+            &mut svg,
+            i,
+            arrays.len(),
+            arr.len(),
+            mode,
+            dims,
+            (-dims.0 / 2.0, -dims.1 / 2.0),
+            doughnut_ratio,
+        );
+    }
+    svg
+}
+
+// Up to 3 decimal places, without trailing zeros, to keep files small.
+fn number(value: f32) -> String {
+    let text = format!("{:.3}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" { String::from("0") } else { text.to_string() }
+}
+
+// e.g. fill="#ff0000", with an opacity if the colour is transparent.
+fn paint(attribute: &str, colour: Colour) -> String {
+    let byte = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let mut text = format!("{}=\"#{:02x}{:02x}{:02x}\"", attribute, byte(colour.r), byte(colour.g), byte(colour.b));
+    if colour.a < 1.0 {
+        let _ = write!(text, " {}-opacity=\"{}\"", attribute, number(colour.a));
+    }
+    text
+}
+
+impl Canvas for Svg {
+    fn line(&mut self, start: Point, end: Point, weight: f32, colour: Colour) {
+        let ((x1, y1), (x2, y2)) = (self.point(start), self.point(end));
+        let _ = writeln!(
+            self.body,
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke-width=\"{}\" {}/>",
+            x1, y1, x2, y2, number(weight), paint("stroke", colour),
+        );
+    }
+
+    fn rect(&mut self, centre: Point, dims: (f32, f32), colour: Colour) {
+        let (x, y) = self.point([centre[0] - dims.0 / 2.0, centre[1] + dims.1 / 2.0]);
+        let _ = writeln!(
+            self.body,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" {}/>",
+            x, y, number(dims.0), number(dims.1), paint("fill", colour),
+        );
+    }
+
+    fn rect_outline(&mut self, centre: Point, dims: (f32, f32), weight: f32, colour: Colour) {
+        let (x, y) = self.point([centre[0] - dims.0 / 2.0, centre[1] + dims.1 / 2.0]);
+        let _ = writeln!(
+            self.body,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke-width=\"{}\" {}/>",
+            x, y, number(dims.0), number(dims.1), number(weight), paint("stroke", colour),
+        );
+    }
+
+    fn tri(&mut self, points: [Point; 3], colour: Colour) {
+        self.polygon(&points, colour);
+    }
+
+    fn quad(&mut self, points: [Point; 4], colour: Colour) {
+        self.polygon(&points, colour);
+    }
+
+    fn ellipse(&mut self, centre: Point, radius: f32, resolution: Option<usize>, colour: Colour) {
+        match resolution {
+            Some(sides) => {
+                let sides = sides.max(3);
+                let points: Vec<Point> = (0..sides)
+                    .map(|i| {
+                        let angle = i as f32 / sides as f32 * TWO_PI;
+                        [centre[0] + radius * angle.cos(), centre[1] + radius * angle.sin()]
+                    })
+                    .collect();
+                self.polygon(&points, colour);
+            },
+            None => {
+                let (x, y) = self.point(centre);
+                let _ = writeln!(self.body, "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" {}/>", x, y, number(radius), paint("fill", colour));
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::snapshot;
+    use crate::sorting_array::{
+        DisplayMode, ShuffleType, SortArray,
+        distribution::Distribution,
+        highlight::{ACTIVE, PIVOT},
+        tests::registry,
+    };
+
+    #[test]
+    fn svg_snapshots_keep_highlights_and_the_window_geometry() {
+        let mut array = SortArray::new(0, Arc::new(registry()), Distribution::Sorted, ShuffleType::FisherYates, Duration::default(), 0);
+        array.set_values(vec![0, 1, 2, 3]);
+        {
+            let mut data = array.data.write().unwrap();
+            data.set_marker(ACTIVE, Some(1));
+            data.set_marker(PIVOT, Some(3));
+        }
+
+        let text = snapshot(&[array], DisplayMode::Bars, (4.0, 4.0), 0.5).finish();
+        assert!(text.starts_with("<svg"));
+        assert_eq!(text.matches("<line").count(), 4);
+        // Bars go up from the bottom, with y flipped from the window's.
+        assert!(text.contains("<line x1=\"0.5\" y1=\"4\" x2=\"0.5\" y2=\"3\" stroke-width=\"1\""));
+        assert!(text.contains("<line x1=\"1.5\" y1=\"4\" x2=\"1.5\" y2=\"2\" stroke-width=\"1\" stroke=\"#0033ff\"/>"));
+        assert!(text.contains("<line x1=\"3.5\" y1=\"4\" x2=\"3.5\" y2=\"0\" stroke-width=\"1\" stroke=\"#d967e4\"/>"));
+    }
+}
@@ -2,3 +2,22 @@
 pub fn get_point_on_radius(rad: f32, angle: f32) -> [f32; 2] {
     [rad * angle.cos(), rad * angle.sin()]
 }
+
+// Hue, saturation and value (all 0 -> 1) to red, green and blue.
+pub fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> (f32, f32, f32) {
+    let sector = (hue.fract() * 6.0).floor();
+    let offset = hue.fract() * 6.0 - sector;
+
+    let p = value * (1.0 - saturation);
+    let q = value * (1.0 - saturation * offset);
+    let t = value * (1.0 - saturation * (1.0 - offset));
+
+    match sector as u8 {
+        0 => (value, t, p),
+        1 => (q, value, p),
+        2 => (p, value, t),
+        3 => (p, q, value),
+        4 => (t, p, value),
+        _ => (value, p, q),
+    }
+}
@@ -0,0 +1,356 @@
+// Terminal frontend, for when there is no display (e.g. over SSH). Draws a single array as bars of Unicode block
+// characters in ANSI colours, with the same keys as the window where they make sense, running the same sort threads.
+
+use std::io::{self, Stdout, Write};
+use std::time::Duration;
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::{Color, Print, ResetColor, SetForegroundColor},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use crate::{
+    config::{self, Config},
+    sorting_array::{
+        SortArray, SortInstruction,
+        distribution::Distribution,
+        file,
+        trace::TracePlayer,
+    },
+};
+
+const FRAME_TIME: Duration = Duration::from_millis(33);
+const BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];     // Indexed by eighths filled
+
+struct Tui {
+    array: SortArray,
+    distribution: Distribution,
+    config: Config,
+    player: Option<TracePlayer>,
+    show_help: bool,
+    message: Option<String>,    // Shown along the bottom, e.g. where the array was saved
+    last_bars: Vec<(char, Color)>,  // Cells drawn last frame, so that only the ones that changed are drawn again
+    last_bars_area: (usize, usize, usize),  // Width, top and rows of the last frame's bars
+}
+
+// Puts the terminal back how it was when dropped, even if the visualiser panics.
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter(stdout: &mut Stdout) -> crossterm::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(stdout, EnterAlternateScreen, Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+pub fn run() {
+    let mut tui = Tui::new();
+    let mut stdout = io::stdout();
+
+    let result = RawTerminal::enter(&mut stdout).and_then(|_terminal| tui.run(&mut stdout));
+    if let Err(err) = result {
+        eprintln!("Terminal error: {}", err);
+    }
+}
+
+// Loads the config, with the seed from the command line if one was given.
+fn load_config() -> Config {
+    let config_obj = Config::from(&config::load_config_file().expect("Could not load config file.")[0]);
+    if let Some(seed) = crate::seed_arg() {
+        config_obj.sorts.rng.reseed(seed);
+    }
+    config_obj
+}
+
+impl Tui {
+    fn new() -> Self {
+        let config_obj = load_config();
+        let mut array = SortArray::new(
+            config_obj.array_len,
+            config_obj.sorts.clone(),
+            config_obj.distribution,
+            config_obj.shuffle_type,
+            config_obj.shuffle_sleep,
+            config_obj.trace_limit,
+        );
+        if let Some(path) = crate::load_arg() {
+            let values = file::load(&path).expect("Could not load array file.");
+            array.load(file::display_name(&path), values);
+        }
+
+        Self {
+            array,
+            distribution: config_obj.distribution,
+            config: config_obj,
+            player: None,
+            show_help: false,
+            message: None,
+            last_bars: Vec::new(),
+            last_bars_area: (0, 0, 0),
+        }
+    }
+
+    fn run(&mut self, stdout: &mut Stdout) -> crossterm::Result<()> {
+        loop {
+            if event::poll(FRAME_TIME)? {
+                match event::read()? {
+                    Event::Key(key) => {
+                        if !self.key_pressed(key) {
+                            return Ok(());
+                        }
+                    },
+                    Event::Resize(_, _) => {
+                        queue!(stdout, Clear(ClearType::All))?;
+                        self.last_bars.clear();
+                    },
+                    Event::Mouse(_) => (),
+                }
+            }
+
+            if let Some(player) = self.player.as_mut() {
+                player.update(&mut self.array.data.write().unwrap());
+            }
+            self.draw(stdout)?;
+        }
+    }
+
+    // Returns false when the visualiser should quit.
+    fn key_pressed(&mut self, key: KeyEvent) -> bool {
+        if key.code == KeyCode::Esc || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)) {
+            return false;
+        }
+
+        if self.player.is_some() {
+            self.replay_key(key.code);
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Char(c) => {
+                let shift = c.is_ascii_uppercase() || key.modifiers.contains(KeyModifiers::SHIFT);
+
+                match c.to_ascii_lowercase() {
+                    't' => self.toggle_replay(),
+                    's' => self.array.instruction(SortInstruction::Shuffle),
+                    'r' => {
+                        if shift {
+                            self.restart_from_seed()
+                        } else {
+                            self.array.instruction(SortInstruction::Reset)
+                        }
+                    },
+                    'i' => self.array.instruction(SortInstruction::Reverse),
+                    'w' => {
+                        let path = file::new_save_path();
+                        self.message = Some(match file::save(&path, &self.array.values()) {
+                            Ok(()) => format!("Saved array to {}", path.display()),
+                            Err(err) => format!("Could not save array: {}", err),
+                        });
+                    },
+                    'v' => {
+                        self.distribution = self.distribution.cycle(shift);
+                        self.array.instruction(SortInstruction::Stop);
+                        self.array.set_distribution(self.distribution);
+                    },
+                    'l' if shift => self.reload_config(),
+                    'q' => self.array.instruction(SortInstruction::Stop),
+                    ' ' => {
+                        if self.array.is_paused() {
+                            self.array.instruction(SortInstruction::Resume)
+                        } else {
+                            self.array.instruction(SortInstruction::Pause)
+                        }
+                    },
+                    'h' => self.show_help = !self.show_help,
+                    c => {
+                        // Sort keys come from the sort registry
+                        if let Some(id) = self.config.sorts.find_by_key(c) {
+                            self.array.instruction(SortInstruction::Sort(id))
+                        }
+                    },
+                }
+            },
+            KeyCode::Right => self.array.instruction(SortInstruction::Step),
+            _ => (),
+        }
+        true
+    }
+
+    fn replay_key(&mut self, code: KeyCode) {
+        if code == KeyCode::Char('t') {
+            self.toggle_replay();
+            return;
+        }
+
+        let player = self.player.as_mut().unwrap();
+        let mut data = self.array.data.write().unwrap();
+
+        match code {
+            KeyCode::Char(' ') => player.playing = !player.playing,
+            KeyCode::Right => {
+                player.playing = false;
+                player.step_forward(&mut data);
+            },
+            KeyCode::Left => {
+                player.playing = false;
+                player.step_back(&mut data);
+            },
+            KeyCode::Char('.') => player.set_direction(true),
+            KeyCode::Char(',') => player.set_direction(false),
+            KeyCode::Up => player.change_speed(2.0),
+            KeyCode::Down => player.change_speed(0.5),
+            KeyCode::Home => player.seek(0, &mut data),
+            KeyCode::End => player.seek(player.len(), &mut data),
+            _ => (),
+        }
+    }
+
+    fn toggle_replay(&mut self) {
+        if let Some(player) = self.player.take() {
+            self.array.return_trace(player.into_trace());
+        } else if let Some(trace) = self.array.take_trace() {
+            self.player = Some(TracePlayer::new(trace));
+        }
+    }
+
+    fn restart_from_seed(&mut self) {
+        self.array.instruction(SortInstruction::Stop);
+        self.config.sorts.rng.reseed(self.config.sorts.rng.seed());
+        self.array.instruction(SortInstruction::Reset);
+    }
+
+    fn reload_config(&mut self) {
+        self.array.instruction(SortInstruction::Stop);
+        self.player = None;
+        self.config = load_config();
+        self.distribution = self.config.distribution;
+        self.array = SortArray::new(
+            self.config.array_len,
+            self.config.sorts.clone(),
+            self.distribution,
+            self.config.shuffle_type,
+            self.config.shuffle_sleep,
+            self.config.trace_limit,
+        );
+    }
+
+    // Stats (and help) along the top, the bars below them and a status line along the bottom.
+    fn draw(&mut self, stdout: &mut Stdout) -> crossterm::Result<()> {
+        let (width, height) = terminal::size()?;
+        let width = width as usize;
+
+        let stats = self.array.stats_text();
+        let mut help = vec![String::from("Sorts:")];
+        if self.show_help {
+            help.extend(self.config.sorts.key_list());
+        }
+        let header_height = stats.lines().count().max(if self.show_help { help.len() } else { 0 });
+        let bar_rows = (height as usize).saturating_sub(header_height + 1).max(1);
+
+        queue!(stdout, SetForegroundColor(Color::White))?;
+        for (row, line) in stats.lines().enumerate() {
+            queue!(stdout, MoveTo(0, row as u16), Clear(ClearType::UntilNewLine), Print(line))?;
+        }
+        for row in stats.lines().count()..header_height {
+            queue!(stdout, MoveTo(0, row as u16), Clear(ClearType::UntilNewLine))?;
+        }
+        if self.show_help {
+            for (row, line) in help.iter().enumerate() {
+                let column = width.saturating_sub(line.chars().count());
+                queue!(stdout, MoveTo(column as u16, row as u16), Print(line))?;
+            }
+        }
+
+        self.draw_bars(stdout, width, header_height, bar_rows)?;
+
+        let status = match self.player.as_ref() {
+            Some(player) => player.label(),
+            None => self.message.clone().unwrap_or_else(|| String::from("H: list sorts, Esc: quit")),
+        };
+        let status: String = status.chars().take(width).collect();
+        queue!(
+            stdout,
+            SetForegroundColor(Color::White),
+            MoveTo(0, (header_height + bar_rows) as u16),
+            Clear(ClearType::UntilNewLine),
+            Print(status),
+        )?;
+
+        stdout.flush()?;
+        Ok(())
+    }
+
+    // Each column shows one element, preferring a highlighted one when there are more elements than columns, and is
+    // filled in eighths of a row. Terminals can be slow (especially over SSH), so only cells that changed are drawn.
+    fn draw_bars(&mut self, stdout: &mut Stdout, width: usize, top: usize, rows: usize) -> crossterm::Result<()> {
+        let data_read = self.array.data.read().unwrap();
+        let len = data_read.len();
+
+        let columns: Vec<Option<(usize, Color)>> = (0..width)
+            .map(|column| {
+                let start = column * len / width;
+                let end = ((column + 1) * len / width).max(start + 1).min(len);
+                if start >= len {
+                    return None;
+                }
+
+                let index = (start..end)
+                    .find(|&i| data_read.highlights.colour(i).is_some())
+                    .unwrap_or(start);
+                let eighths = (data_read[index] + 1) * rows * 8 / data_read.max_val.max(1);
+                let (r, g, b) = data_read.element_colour(index);
+
+                Some((eighths, Color::Rgb { r: (r * 255.0) as u8, g: (g * 255.0) as u8, b: (b * 255.0) as u8 }))
+            })
+            .collect();
+        drop(data_read);
+
+        let cells: Vec<(char, Color)> = (0..rows)
+            .flat_map(|row| {
+                let row_bottom = (rows - 1 - row) * 8;     // Eighths below this row
+                columns.iter().map(move |column| {
+                    match column {
+                        Some((eighths, colour)) if *eighths > row_bottom => (BLOCKS[(eighths - row_bottom).min(8)], *colour),
+                        _ => (' ', Color::Reset),
+                    }
+                })
+            })
+            .collect();
+
+        if self.last_bars_area != (width, top, rows) {
+            self.last_bars.clear();
+            self.last_bars_area = (width, top, rows);
+        }
+
+        let mut current_colour = None;
+        let mut cursor = None;      // Where the cursor is left after the last cell drawn
+        for (i, &(block, colour)) in cells.iter().enumerate() {
+            if self.last_bars.get(i) == Some(&(block, colour)) {
+                continue;
+            }
+            if cursor != Some(i) {
+                queue!(stdout, MoveTo((i % width) as u16, (top + i / width) as u16))?;
+            }
+            if current_colour != Some(colour) {
+                queue!(stdout, SetForegroundColor(colour))?;
+                current_colour = Some(colour);
+            }
+            queue!(stdout, Print(block))?;
+            cursor = if (i + 1) % width == 0 { None } else { Some(i + 1) };
+        }
+
+        self.last_bars = cells;
+        Ok(())
+    }
+}